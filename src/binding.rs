@@ -0,0 +1,37 @@
+use crate::{InputModality, Keyborg, KeyborgHandle, KeyborgProps, Subscription};
+use web_sys::Window;
+
+/// A keyborg instance owned by a UI framework provider or hook, disposed when dropped.
+/// All bindings of a window share the same core.
+pub(crate) struct KeyborgBinding {
+    keyborg: KeyborgHandle,
+    _subscriptions: [Subscription; 2],
+}
+
+impl KeyborgBinding {
+    pub fn new(
+        win: Window,
+        props: Option<KeyborgProps>,
+        on_change: impl Fn(bool) + Send + Sync + 'static,
+        on_modality_change: impl Fn(InputModality) + Send + Sync + 'static,
+    ) -> Self {
+        let keyborg = Keyborg::create(win, props);
+        let subscriptions = [
+            keyborg.subscribe(on_change),
+            keyborg.subscribe_modality(on_modality_change),
+        ];
+
+        Self {
+            keyborg,
+            _subscriptions: subscriptions,
+        }
+    }
+
+    pub fn is_navigating_with_keyboard(&self) -> bool {
+        self.keyborg.is_navigating_with_keyboard()
+    }
+
+    pub fn current_modality(&self) -> InputModality {
+        self.keyborg.current_modality()
+    }
+}
@@ -0,0 +1,179 @@
+use js_sys::{Array, Function, Object, Reflect};
+use send_wrapper::SendWrapper;
+use wasm_bindgen::{prelude::Closure, JsCast, JsValue, UnwrapThrowExt};
+use web_sys::Window;
+
+// Where the JavaScript keyborg library keeps its core and instances: `{ core, refs }`.
+const KEYBORG: &str = "__keyborg";
+// Keeps the JavaScript keyborg instances from disposing the core exposed by this crate.
+const EXPOSED_REF: &str = "keyborg-rs";
+
+fn get(target: &JsValue, key: &str) -> Option<JsValue> {
+    Reflect::get(target, &JsValue::from_str(key))
+        .ok()
+        .filter(|value| value.is_object())
+}
+
+/// @returns The core of the JavaScript keyborg library set up in the window
+pub(crate) fn find_js_core(win: &Window) -> Option<JsValue> {
+    get(&get(win, KEYBORG)?, "core")
+}
+
+pub(crate) fn is_js_navigating(js_core: &JsValue) -> bool {
+    Reflect::get(js_core, &JsValue::from_str("isNavigatingWithKeyboard"))
+        .ok()
+        .and_then(|value| value.as_bool())
+        .unwrap_or_default()
+}
+
+/// How the keyboard navigation state is shared with the JavaScript keyborg library.
+#[derive(Clone)]
+pub(crate) enum JsSync {
+    /// The core of the JavaScript keyborg library owns the state.
+    Attached(SendWrapper<JsValue>),
+    /// The state is exposed in `window.__keyborg` for the JavaScript keyborg instances.
+    Exposed,
+}
+
+impl JsSync {
+    /// Passes the new state on to the JavaScript keyborg library.
+    pub fn update(&self, win: &Window, is_navigating_with_keyboard: bool) {
+        match self {
+            Self::Attached(js_core) => {
+                // Its setter notifies the instances, including the ref of this crate.
+                if is_js_navigating(js_core) != is_navigating_with_keyboard {
+                    let _ = Reflect::set(
+                        js_core,
+                        &JsValue::from_str("isNavigatingWithKeyboard"),
+                        &JsValue::from_bool(is_navigating_with_keyboard),
+                    );
+                }
+            }
+            Self::Exposed => {
+                let Some(refs) = get(win, KEYBORG).and_then(|current| get(&current, "refs")) else {
+                    return;
+                };
+                let value = JsValue::from_bool(is_navigating_with_keyboard);
+                for instance in Object::values(refs.unchecked_ref()) {
+                    let Some(callbacks) = get(&instance, "_cb") else {
+                        continue;
+                    };
+                    for callback in Array::from(&callbacks) {
+                        if let Some(callback) = callback.dyn_ref::<Function>() {
+                            let _ = callback.call1(&JsValue::undefined(), &value);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// An instance registered in the `refs` of the JavaScript keyborg library, notified like its
+/// own instances and removed when dropped.
+pub(crate) struct JsRef {
+    win: SendWrapper<Window>,
+    refs: SendWrapper<JsValue>,
+    id: String,
+}
+
+impl JsRef {
+    pub fn new(win: &Window, id: String, callback: impl Fn(bool) + 'static) -> Option<Self> {
+        let refs = get(&get(win, KEYBORG)?, "refs")?;
+
+        let callback = Closure::<dyn Fn(bool)>::new(callback).into_js_value();
+        let instance = Object::new();
+        Reflect::set(&instance, &JsValue::from_str("_cb"), &Array::of1(&callback)).ok()?;
+        Reflect::set(&refs, &JsValue::from_str(&id), &instance).ok()?;
+
+        Some(Self {
+            win: SendWrapper::new(win.clone()),
+            refs: SendWrapper::new(refs),
+            id,
+        })
+    }
+}
+
+impl Drop for JsRef {
+    fn drop(&mut self) {
+        let _ = Reflect::delete_property(
+            self.refs.unchecked_ref::<Object>(),
+            &JsValue::from_str(&self.id),
+        );
+
+        // Disposes the core like the last JavaScript keyborg instance would.
+        if Object::keys(self.refs.unchecked_ref::<Object>()).length() > 0 {
+            return;
+        }
+        let Some(current) = get(&self.win, KEYBORG) else {
+            return;
+        };
+        if get(&current, "refs").as_ref() != Some(&*self.refs) {
+            return;
+        }
+        if let Some(js_core) = get(&current, "core") {
+            if let Ok(dispose) = Reflect::get(&js_core, &JsValue::from_str("dispose"))
+                .and_then(JsCast::dyn_into::<Function>)
+            {
+                let _ = dispose.call0(&js_core);
+            }
+        }
+        let _ = Reflect::delete_property(&self.win, &JsValue::from_str(KEYBORG));
+    }
+}
+
+/// The state of a core of this crate exposed in `window.__keyborg`, so that the JavaScript
+/// keyborg instances created later use it instead of setting up their own core.
+pub(crate) struct JsAdapter {
+    win: SendWrapper<Window>,
+    core: SendWrapper<JsValue>,
+}
+
+impl JsAdapter {
+    pub fn new(
+        win: &Window,
+        get_state: impl Fn() -> bool + 'static,
+        set_state: impl Fn(bool) + 'static,
+    ) -> Self {
+        let core = Object::new();
+        let descriptor = Object::new();
+        let getter = Closure::<dyn Fn() -> bool>::new(get_state).into_js_value();
+        let setter = Closure::<dyn Fn(bool)>::new(set_state).into_js_value();
+        Reflect::set(&descriptor, &JsValue::from_str("get"), &getter).unwrap_throw();
+        Reflect::set(&descriptor, &JsValue::from_str("set"), &setter).unwrap_throw();
+        Object::define_property(
+            &core,
+            &JsValue::from_str("isNavigatingWithKeyboard"),
+            &descriptor,
+        );
+        // This crate disposes the core, not the JavaScript keyborg instances.
+        let dispose = Closure::<dyn Fn()>::new(|| {}).into_js_value();
+        Reflect::set(&core, &JsValue::from_str("dispose"), &dispose).unwrap_throw();
+
+        let placeholder = Object::new();
+        Reflect::set(&placeholder, &JsValue::from_str("_cb"), &Array::new()).unwrap_throw();
+        let refs = Object::new();
+        Reflect::set(&refs, &JsValue::from_str(EXPOSED_REF), &placeholder).unwrap_throw();
+
+        let current = Object::new();
+        Reflect::set(&current, &JsValue::from_str("core"), &core).unwrap_throw();
+        Reflect::set(&current, &JsValue::from_str("refs"), &refs).unwrap_throw();
+        Reflect::set(win, &JsValue::from_str(KEYBORG), &current).unwrap_throw();
+
+        Self {
+            win: SendWrapper::new(win.clone()),
+            core: SendWrapper::new(core.into()),
+        }
+    }
+}
+
+impl Drop for JsAdapter {
+    fn drop(&mut self) {
+        let Some(current) = get(&self.win, KEYBORG) else {
+            return;
+        };
+        if get(&current, "core").as_ref() == Some(&*self.core) {
+            let _ = Reflect::delete_property(&self.win, &JsValue::from_str(KEYBORG));
+        }
+    }
+}
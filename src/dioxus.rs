@@ -0,0 +1,121 @@
+use crate::{binding::KeyborgBinding, InputModality, KeyborgProps};
+use dioxus_lib::prelude::*;
+use send_wrapper::SendWrapper;
+use std::{cell::RefCell, rc::Rc};
+use wasm_bindgen::UnwrapThrowExt;
+
+#[derive(Clone, Copy)]
+struct KeyborgContext {
+    is_navigating_with_keyboard: Signal<bool>,
+    modality: Signal<InputModality>,
+}
+
+impl KeyborgContext {
+    /// The signals start at their defaults until the binding is created after mount.
+    fn new() -> Self {
+        Self {
+            is_navigating_with_keyboard: Signal::new(false),
+            modality: Signal::new(InputModality::default()),
+        }
+    }
+
+    fn bind(self, props: Option<KeyborgProps>) -> KeyborgBinding {
+        let Self {
+            mut is_navigating_with_keyboard,
+            mut modality,
+        } = self;
+
+        let binding = KeyborgBinding::new(
+            web_sys::window().unwrap_throw(),
+            props,
+            {
+                let signal = SendWrapper::new(is_navigating_with_keyboard);
+                move |value| {
+                    // Ignores changes arriving after the scope was dropped.
+                    let mut signal = *signal;
+                    if let Ok(mut current) = signal.try_write() {
+                        *current = value;
+                    };
+                }
+            },
+            {
+                let signal = SendWrapper::new(modality);
+                move |value| {
+                    // Ignores changes arriving after the scope was dropped.
+                    let mut signal = *signal;
+                    if let Ok(mut current) = signal.try_write() {
+                        *current = value;
+                    };
+                }
+            },
+        );
+        is_navigating_with_keyboard.set(binding.is_navigating_with_keyboard());
+        modality.set(binding.current_modality());
+
+        binding
+    }
+}
+
+/// Creates the keyborg instance of `context` in an effect, so nothing runs during server
+/// rendering. The instance is owned by the current scope and disposed with its hooks.
+fn use_keyborg_binding(context: KeyborgContext, props: Option<KeyborgProps>, enabled: bool) {
+    let binding = use_hook(|| Rc::new(RefCell::new(None::<KeyborgBinding>)));
+    let mut props = Some(props);
+
+    use_effect(move || {
+        let mut binding = binding.borrow_mut();
+        if enabled && binding.is_none() {
+            *binding = Some(context.bind(props.take().flatten()));
+        }
+    });
+}
+
+/// Provides a keyborg instance to the descendants of the current component, shared by
+/// `use_keyborg` and `use_keyborg_modality`. `props` are only read on the first render.
+pub fn use_keyborg_provider(props: Option<KeyborgProps>) {
+    let context = use_hook(|| provide_context(KeyborgContext::new()));
+    use_keyborg_binding(context, props, true);
+}
+
+#[derive(Props, Clone)]
+pub struct KeyborgProviderProps {
+    /// Only read when the provider is created.
+    #[props(default)]
+    props: Option<KeyborgProps>,
+    children: Element,
+}
+
+impl PartialEq for KeyborgProviderProps {
+    fn eq(&self, other: &Self) -> bool {
+        self.children == other.children
+    }
+}
+
+/// Provides a keyborg instance to its children.
+#[allow(non_snake_case)]
+pub fn KeyborgProvider(props: KeyborgProviderProps) -> Element {
+    use_keyborg_provider(props.props);
+
+    props.children
+}
+
+fn use_keyborg_context() -> KeyborgContext {
+    let (context, is_provided) = use_hook(|| match try_consume_context::<KeyborgContext>() {
+        Some(context) => (context, true),
+        None => (KeyborgContext::new(), false),
+    });
+    use_keyborg_binding(context, None, !is_provided);
+
+    context
+}
+
+/// @returns Whether the user is navigating with keyboard, using the provided keyborg
+/// instance or creating one scoped to the current component.
+pub fn use_keyborg() -> ReadOnlySignal<bool> {
+    use_keyborg_context().is_navigating_with_keyboard.into()
+}
+
+/// @returns How the user is currently interacting with the page
+pub fn use_keyborg_modality() -> ReadOnlySignal<InputModality> {
+    use_keyborg_context().modality.into()
+}
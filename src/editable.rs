@@ -0,0 +1,118 @@
+use std::{fmt, sync::Arc};
+use web_sys::{wasm_bindgen::JsCast, Document, Element, HtmlElement, HtmlIFrameElement};
+
+type EditableClassifierFn = dyn Fn(&Element) -> Option<bool> + Send + Sync;
+
+/// App provided rule telling whether an element is editable, `None` falls back to the
+/// built-in rules.
+#[derive(Clone)]
+pub(crate) struct EditableClassifier(Arc<EditableClassifierFn>);
+
+impl EditableClassifier {
+    pub fn new(classifier: impl Fn(&Element) -> Option<bool> + Send + Sync + 'static) -> Self {
+        Self(Arc::new(classifier))
+    }
+}
+
+impl fmt::Debug for EditableClassifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("EditableClassifier").finish_non_exhaustive()
+    }
+}
+
+/// @returns The focused element, looking into the open shadow roots and the same-origin
+/// frames
+pub(crate) fn deep_active_element(doc: &Document) -> Option<Element> {
+    let mut active_element = doc.active_element()?;
+
+    while let Some(el) = active_element
+        .shadow_root()
+        .and_then(|shadow_root| shadow_root.active_element())
+        .or_else(|| {
+            active_element
+                .dyn_ref::<HtmlIFrameElement>()?
+                .content_document()?
+                .active_element()
+        })
+    {
+        active_element = el;
+    }
+
+    Some(active_element)
+}
+
+/// @returns Whether the focused element takes the keys as text input, so that they
+/// are not navigation keys
+pub(crate) fn is_editable_active_element(
+    doc: &Document,
+    classifiers: &[EditableClassifier],
+) -> bool {
+    let Some(el) = deep_active_element(doc) else {
+        return false;
+    };
+
+    if let Some(is_editable) = classifiers.iter().find_map(|classifier| classifier.0(&el)) {
+        return is_editable;
+    }
+
+    if let Some(role) = el.get_attribute("role") {
+        if role.split_ascii_whitespace().any(is_editable_role) {
+            return true;
+        }
+    }
+
+    match el.local_name().as_str() {
+        "input" => is_text_input_type(&el.get_attribute("type").unwrap_or_default()),
+        "textarea" => true,
+        _ => el
+            .dyn_ref::<HtmlElement>()
+            .is_some_and(|el| el.is_content_editable()),
+    }
+}
+
+/// @returns Whether an `<input>` of the type takes text, unknown types fall back to text
+fn is_text_input_type(input_type: &str) -> bool {
+    !matches!(
+        input_type.to_ascii_lowercase().as_str(),
+        "checkbox"
+            | "radio"
+            | "range"
+            | "button"
+            | "submit"
+            | "reset"
+            | "image"
+            | "file"
+            | "color"
+            | "hidden"
+    )
+}
+
+fn is_editable_role(role: &str) -> bool {
+    matches!(role, "textbox" | "searchbox" | "combobox" | "spinbutton")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_types() {
+        assert!(is_text_input_type(""));
+        assert!(is_text_input_type("text"));
+        assert!(is_text_input_type("email"));
+        assert!(is_text_input_type("unknown"));
+        assert!(!is_text_input_type("checkbox"));
+        assert!(!is_text_input_type("Radio"));
+        assert!(!is_text_input_type("range"));
+        assert!(!is_text_input_type("submit"));
+    }
+
+    #[test]
+    fn roles() {
+        assert!(is_editable_role("textbox"));
+        assert!(is_editable_role("combobox"));
+        assert!(is_editable_role("spinbutton"));
+        assert!(!is_editable_role("button"));
+        assert!(!is_editable_role("listbox"));
+    }
+}
@@ -0,0 +1,207 @@
+use crate::focus_event::{KEYBORG_FOCUSIN, KEYBORG_FOCUSOUT};
+use gloo_events::{EventListener, EventListenerOptions};
+use js_sys::Reflect;
+use wasm_bindgen::{prelude::wasm_bindgen, JsCast, JsValue};
+use web_sys::{CustomEvent, Element, Event, EventTarget, FocusEvent, HtmlElement};
+
+#[wasm_bindgen]
+extern "C" {
+    // Plain objects, not instances of a class: `dyn_into` must not check with `instanceof`.
+    #[wasm_bindgen(extends = js_sys::Object, is_type_of = JsValue::is_object)]
+    type KeyborgFocusInEventDetails;
+
+    #[wasm_bindgen(method, getter, js_name = relatedTarget)]
+    fn related_target(this: &KeyborgFocusInEventDetails) -> Option<HtmlElement>;
+
+    #[wasm_bindgen(method, getter, js_name = isFocusedProgrammatically)]
+    fn is_focused_programmatically(this: &KeyborgFocusInEventDetails) -> Option<bool>;
+
+    #[wasm_bindgen(method, getter, js_name = originalEvent)]
+    fn original_event(this: &KeyborgFocusInEventDetails) -> Option<FocusEvent>;
+
+    #[wasm_bindgen(method, getter, js_name = focusOptions)]
+    fn focus_options(this: &KeyborgFocusInEventDetails) -> Option<js_sys::Object>;
+
+    #[wasm_bindgen(extends = js_sys::Object, is_type_of = JsValue::is_object)]
+    type KeyborgFocusOutEventDetails;
+
+    #[wasm_bindgen(method, getter, js_name = originalEvent)]
+    fn original_event(this: &KeyborgFocusOutEventDetails) -> Option<FocusEvent>;
+}
+
+/// Whether the focus was moved by calling `focus()`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ProgrammaticFocus {
+    Yes,
+    No,
+    /// The native `focus` could not be overridden, so it is not known.
+    #[default]
+    Unknown,
+}
+
+impl ProgrammaticFocus {
+    pub fn as_bool(self) -> Option<bool> {
+        match self {
+            Self::Yes => Some(true),
+            Self::No => Some(false),
+            Self::Unknown => None,
+        }
+    }
+}
+
+impl From<Option<bool>> for ProgrammaticFocus {
+    fn from(value: Option<bool>) -> Self {
+        match value {
+            Some(true) => Self::Yes,
+            Some(false) => Self::No,
+            None => Self::Unknown,
+        }
+    }
+}
+
+/// The [`KEYBORG_FOCUSIN`] event, dispatched on the focused element, also inside shadow roots.
+#[derive(Debug, Clone)]
+pub struct KeyborgFocusInEvent {
+    /// The focused element, rather than the shadow host the event is retargeted to.
+    pub target: Option<Element>,
+    /// The element losing the focus.
+    pub related_target: Option<HtmlElement>,
+    pub is_focused_programmatically: ProgrammaticFocus,
+    pub original_event: Option<FocusEvent>,
+    /// The `FocusOptions` passed to `focus()`, when the focus was moved programmatically.
+    pub focus_options: Option<js_sys::Object>,
+    /// The `focusVisible` option, when it was explicitly set.
+    pub focus_visible: Option<bool>,
+    pub event: CustomEvent,
+}
+
+impl KeyborgFocusInEvent {
+    /// @returns `None` when the event is not a keyborg focus in event
+    pub fn from_event(event: &Event) -> Option<Self> {
+        let event = event.dyn_ref::<CustomEvent>()?;
+        if event.type_() != KEYBORG_FOCUSIN {
+            return None;
+        }
+        let details = event
+            .detail()
+            .dyn_into::<KeyborgFocusInEventDetails>()
+            .ok()?;
+
+        let focus_options = details.focus_options();
+        let focus_visible = focus_options.as_ref().and_then(|options| {
+            Reflect::get(options, &JsValue::from_str("focusVisible"))
+                .ok()?
+                .as_bool()
+        });
+
+        Some(Self {
+            target: composed_target(event),
+            related_target: details.related_target(),
+            is_focused_programmatically: details.is_focused_programmatically().into(),
+            original_event: details.original_event(),
+            focus_options,
+            focus_visible,
+            event: event.clone(),
+        })
+    }
+}
+
+/// The [`KEYBORG_FOCUSOUT`] event, dispatched on the element losing the focus.
+#[derive(Debug, Clone)]
+pub struct KeyborgFocusOutEvent {
+    /// The element losing the focus, rather than the shadow host the event is retargeted to.
+    pub target: Option<Element>,
+    pub original_event: Option<FocusEvent>,
+    pub event: CustomEvent,
+}
+
+impl KeyborgFocusOutEvent {
+    /// @returns `None` when the event is not a keyborg focus out event
+    pub fn from_event(event: &Event) -> Option<Self> {
+        let event = event.dyn_ref::<CustomEvent>()?;
+        if event.type_() != KEYBORG_FOCUSOUT {
+            return None;
+        }
+        let details = event
+            .detail()
+            .dyn_into::<KeyborgFocusOutEventDetails>()
+            .ok()?;
+
+        Some(Self {
+            target: composed_target(event),
+            original_event: details.original_event(),
+            event: event.clone(),
+        })
+    }
+}
+
+/// The element dispatching the event, rather than the shadow host it is retargeted to.
+pub(crate) fn composed_target(event: &Event) -> Option<Element> {
+    event.composed_path().at(0).dyn_into::<Element>().ok()
+}
+
+/// Listens to the [`KEYBORG_FOCUSIN`] events reaching `target`, in the capture phase.
+///
+/// @returns The listener, removed when dropped
+pub fn on_keyborg_focus_in(
+    target: &EventTarget,
+    mut callback: impl FnMut(KeyborgFocusInEvent) + 'static,
+) -> EventListener {
+    let options = EventListenerOptions::run_in_capture_phase();
+    EventListener::new_with_options(target, KEYBORG_FOCUSIN, options, move |event| {
+        if let Some(event) = KeyborgFocusInEvent::from_event(event) {
+            callback(event);
+        }
+    })
+}
+
+/// Listens to the [`KEYBORG_FOCUSOUT`] events reaching `target`, in the capture phase.
+///
+/// @returns The listener, removed when dropped
+pub fn on_keyborg_focus_out(
+    target: &EventTarget,
+    mut callback: impl FnMut(KeyborgFocusOutEvent) + 'static,
+) -> EventListener {
+    let options = EventListenerOptions::run_in_capture_phase();
+    EventListener::new_with_options(target, KEYBORG_FOCUSOUT, options, move |event| {
+        if let Some(event) = KeyborgFocusOutEvent::from_event(event) {
+            callback(event);
+        }
+    })
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+    use web_sys::CustomEventInit;
+
+    fn custom_event(type_: &str, detail: &js_sys::Object) -> CustomEvent {
+        let init = CustomEventInit::new();
+        init.set_detail(detail);
+        CustomEvent::new_with_event_init_dict(type_, &init).unwrap()
+    }
+
+    #[wasm_bindgen_test]
+    fn focus_in_details_are_read_from_a_plain_object() {
+        let detail = js_sys::Object::new();
+        Reflect::set(&detail, &"isFocusedProgrammatically".into(), &true.into()).unwrap();
+        let options = js_sys::Object::new();
+        Reflect::set(&options, &"focusVisible".into(), &false.into()).unwrap();
+        Reflect::set(&detail, &"focusOptions".into(), &options).unwrap();
+
+        let event = KeyborgFocusInEvent::from_event(&custom_event(KEYBORG_FOCUSIN, &detail))
+            .expect("a keyborg focus in event");
+        assert_eq!(event.is_focused_programmatically, ProgrammaticFocus::Yes);
+        assert_eq!(event.focus_visible, Some(false));
+        assert!(event.related_target.is_none());
+    }
+
+    #[wasm_bindgen_test]
+    fn focus_out_details_are_read_from_a_plain_object() {
+        let detail = js_sys::Object::new();
+        let event = custom_event(KEYBORG_FOCUSOUT, &detail);
+        assert!(KeyborgFocusOutEvent::from_event(&event).is_some());
+        assert!(KeyborgFocusInEvent::from_event(&event).is_none());
+    }
+}
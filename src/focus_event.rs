@@ -1,10 +1,11 @@
-use crate::js::WeakRef;
+use crate::js::{override_focus, WeakRef};
 use gloo_events::{EventListener, EventListenerOptions};
 use js_sys::{Reflect, Set};
 use send_wrapper::SendWrapper;
 use std::{
     cell::RefCell,
     collections::HashMap,
+    rc::Rc,
     sync::{Arc, LazyLock, RwLock},
 };
 use wasm_bindgen::prelude::*;
@@ -13,14 +14,15 @@ use web_sys::{
     ShadowRoot, Window,
 };
 
-pub const KEYBORG_FOCUSIN: &'static str = "keyborg:focusin";
-pub const KEYBORG_FOCUSOUT: &'static str = "keyborg:focusout";
+pub const KEYBORG_FOCUSIN: &str = "keyborg:focusin";
+pub const KEYBORG_FOCUSOUT: &str = "keyborg:focusout";
 
-static EVENT_LISTENER_MAP: LazyLock<RwLock<EventListenerMap>> =
-    LazyLock::new(move || Default::default());
+// Not `__keyborgData`, which belongs to the JavaScript keyborg library when both are on the page.
+const KEYBORG_DATA: &str = "__keyborgRsData";
 
-static KEYBORG_DATA_LIST: LazyLock<RwLock<KeyborgDataList>> =
-    LazyLock::new(move || Default::default());
+static EVENT_LISTENER_MAP: LazyLock<RwLock<EventListenerMap>> = LazyLock::new(Default::default);
+
+static KEYBORG_DATA_LIST: LazyLock<RwLock<KeyborgDataList>> = LazyLock::new(Default::default);
 
 #[derive(Debug, Default)]
 struct EventListenerMap(HashMap<&'static str, Vec<SendWrapper<(EventTarget, EventListener)>>>);
@@ -58,9 +60,33 @@ impl KeyborgDataList {
         self.0.push((SendWrapper::new(win), data));
     }
 
-    pub fn remove(&mut self, win: &Window) {
-        if let Some(index) = self.0.iter().position(|v| *v.0 == *win) {
-            self.0.remove(index);
+    pub fn remove(&mut self, win: &Window) -> Option<KeyborgData> {
+        let index = self.0.iter().position(|v| *v.0 == *win)?;
+        Some(self.0.remove(index).1)
+    }
+
+    /// Counts another owner of the setup of the window, like a core and the core of a parent
+    /// window instrumenting its frame.
+    ///
+    /// @returns Whether the window is already set up
+    pub fn acquire(&mut self, win: &Window) -> bool {
+        match self.0.iter_mut().find(|v| *v.0 == *win) {
+            Some((_, data)) => {
+                data.owners += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// @returns Whether other owners still use the setup of the window
+    pub fn release(&mut self, win: &Window) -> bool {
+        match self.0.iter_mut().find(|v| *v.0 == *win) {
+            Some((_, data)) if data.owners > 1 => {
+                data.owners -= 1;
+                true
+            }
+            _ => false,
         }
     }
 
@@ -74,23 +100,26 @@ impl KeyborgDataList {
 }
 
 struct KeyborgData {
+    // The setups of the window not disposed yet.
+    owners: usize,
+    is_focus_overridden: bool,
+    restore_focus: Option<SendWrapper<js_sys::Function>>,
     focus_in_handler: Arc<dyn Fn(&Event) + Send + Sync + 'static>,
     focus_out_handler: Arc<dyn Fn(&Event) + Send + Sync + 'static>,
 }
 
 fn can_override_native_focus(win: &Window) -> bool {
-    let html_element = win.get("HTMLElemnt").unwrap_throw();
+    let html_element = win.get("HTMLElement").unwrap_throw();
     let prototype = Reflect::get(&html_element, &JsValue::from_str("prototype")).unwrap_throw();
     let js_focus = JsValue::from_str("focus");
     let orig_focus = Reflect::get(&prototype, &js_focus).unwrap_throw();
 
-    let is_custom_focus_called = Arc::new(RefCell::new(false));
+    let is_custom_focus_called = Rc::new(RefCell::new(false));
 
     let focus_closure: Closure<dyn FnMut()> = Closure::new({
         let is_custom_focus_called = is_custom_focus_called.clone();
         move || {
             *is_custom_focus_called.borrow_mut() = true;
-            ()
         }
     });
     Reflect::set(&prototype, &js_focus, &focus_closure.into_js_value()).unwrap_throw();
@@ -110,46 +139,23 @@ fn can_override_native_focus(win: &Window) -> bool {
     rt
 }
 
-static CAN_OVERRIDE_NATIVE_FOCUS: LazyLock<RwLock<bool>> =
-    LazyLock::new(move || RwLock::new(false));
-
-#[wasm_bindgen]
-extern "C" {
-    #[wasm_bindgen(extends = js_sys::Object)]
-    pub type KeyborgFocusInEventDetails;
-
-    #[wasm_bindgen(method, getter, js_name = relatedTarget)]
-    pub fn related_target(this: &KeyborgFocusInEventDetails) -> web_sys::HtmlElement;
-
-    #[wasm_bindgen(method, getter, js_name = isFocusedProgrammatically)]
-    pub fn is_focused_programmatically(this: &KeyborgFocusInEventDetails) -> bool;
-
-    #[wasm_bindgen(method, getter, js_name = originalEvent)]
-    pub fn original_event(this: &KeyborgFocusInEventDetails) -> web_sys::FocusEvent;
-}
-
-/// Overrides the native `focus` and setups the keyborg focus event
+/// Overrides the native `focus` and setups the keyborg focus event, each call is to be
+/// balanced with a [`dispose_focus_event`]
 pub fn setup_focus_event(win: &Window) {
     let kwin = win;
 
-    if !*CAN_OVERRIDE_NATIVE_FOCUS.read().unwrap_throw() {
-        *CAN_OVERRIDE_NATIVE_FOCUS.write().unwrap_throw() = can_override_native_focus(kwin);
-    }
-
-    let html_element = kwin.get("HTMLElemnt").unwrap_throw();
-    let prototype = Reflect::get(&html_element, &JsValue::from_str("prototype")).unwrap_throw();
-    let js_focus = JsValue::from_str("focus");
-    let orig_focus = Reflect::get(&prototype, &js_focus).unwrap_throw();
-    if Reflect::has(&orig_focus, &JsValue::from_str("__keyborgNativeFocus")).unwrap_throw() {
-        // Already set up.
+    if KEYBORG_DATA_LIST.write().unwrap_throw().acquire(kwin) {
+        // Already set up, the probe would call the overridden `focus`.
         return;
     }
 
+    // Each window has its own `HTMLElement.prototype`.
+    let can_override_native_focus = can_override_native_focus(kwin);
+
     let focus = {
         let kwin = win.clone();
-        let orig_focus = orig_focus.clone();
-        move |this: HtmlElement| {
-            let keyborg_native_focus_event = kwin.get("__keyborgData");
+        move |this: HtmlElement, next: js_sys::Function, args: js_sys::Array| {
+            let keyborg_native_focus_event = kwin.get(KEYBORG_DATA);
 
             if let Some(keyborg_native_focus_event) = keyborg_native_focus_event {
                 let _ = Reflect::set(
@@ -157,28 +163,27 @@ pub fn setup_focus_event(win: &Window) {
                     &JsValue::from_str("lastFocusedProgrammatically"),
                     &WeakRef::new(this.clone().into()),
                 );
+                // FocusOptions
+                let _ = Reflect::set(
+                    &keyborg_native_focus_event,
+                    &JsValue::from_str("lastFocusOptions"),
+                    &args.get(0),
+                );
             }
 
-            let orig_focus = orig_focus.dyn_ref::<js_sys::Function>().unwrap_throw();
-            js_sys::Function::apply(orig_focus, &this, &js_sys::Array::new()).unwrap_throw();
+            next.apply(&this, &args).unwrap_throw();
         }
     };
 
-    let _ = Reflect::set(
-        &kwin,
-        &JsValue::from_str("__keyborgNativeFocus"),
-        &orig_focus,
-    );
-
-    let closure = Closure::wrap(Box::new(focus) as Box<dyn Fn(HtmlElement)>);
-    let _ = Reflect::set(
-        &kwin,
-        &JsValue::from_str("__keyborgHTMLElementFocus"),
-        closure.as_ref().unchecked_ref::<js_sys::Function>(),
-    );
-    let _ = js_sys::eval(
-        "HTMLElement.prototype.focus = function focus() { __keyborgHTMLElementFocus(this); }",
-    );
+    let closure =
+        Closure::wrap(Box::new(focus) as Box<dyn Fn(HtmlElement, js_sys::Function, js_sys::Array)>);
+    let restore_focus = override_focus(kwin, &closure.into_js_value());
+    let is_focus_overridden = restore_focus.is_some() && can_override_native_focus;
+    if !is_focus_overridden && cfg!(debug_assertions) {
+        web_sys::console::warn_1(&JsValue::from_str(
+            "Keyborg could not override HTMLElement.prototype.focus, programmatic focus is not detected.",
+        ));
+    }
 
     // Set<WeakRefInstance<ShadowRoot>>
     let shadow_targets = js_sys::Set::default();
@@ -198,7 +203,7 @@ pub fn setup_focus_event(win: &Window) {
         // Allows the event to bubble past an open shadow root
         init.set_composed(true);
         let detail = js_sys::Object::new();
-        let _ = Reflect::set(&detail, &JsValue::from_str("originalEvent"), &e);
+        let _ = Reflect::set(&detail, &JsValue::from_str("originalEvent"), e);
         init.set_detail(&detail);
         let event = CustomEvent::new_with_event_init_dict(KEYBORG_FOCUSOUT, &init).unwrap_throw();
 
@@ -319,16 +324,14 @@ pub fn setup_focus_event(win: &Window) {
             let event =
                 CustomEvent::new_with_event_init_dict(KEYBORG_FOCUSIN, &init).unwrap_throw();
 
-            let data = kwin.get("__keyborgData").unwrap_throw();
+            let data = kwin.get(KEYBORG_DATA).unwrap_throw();
 
             let last_focused_programmatically =
                 Reflect::get(&data, &JsValue::from_str("lastFocusedProgrammatically"))
                     .unwrap_throw();
             let last_focused_programmatically = last_focused_programmatically.dyn_into::<WeakRef>();
 
-            if *CAN_OVERRIDE_NATIVE_FOCUS.read().unwrap_throw()
-                || last_focused_programmatically.is_ok()
-            {
+            if can_override_native_focus || last_focused_programmatically.is_ok() {
                 let is_focused_programmatically =
                     if let Ok(last_focused_programmatically) = last_focused_programmatically {
                         Some(target.clone().into()) == last_focused_programmatically.deref()
@@ -341,12 +344,18 @@ pub fn setup_focus_event(win: &Window) {
                     &JsValue::from_bool(is_focused_programmatically),
                 )
                 .unwrap_throw();
-                Reflect::set(
-                    &data,
-                    &JsValue::from_str("lastFocusedProgrammatically"),
-                    &JsValue::undefined(),
-                )
-                .unwrap_throw();
+                if is_focused_programmatically {
+                    let focus_options =
+                        Reflect::get(&data, &JsValue::from_str("lastFocusOptions")).unwrap_throw();
+                    if focus_options.is_object() {
+                        Reflect::set(&details, &JsValue::from_str("focusOptions"), &focus_options)
+                            .unwrap_throw();
+                    }
+                }
+                for key in ["lastFocusedProgrammatically", "lastFocusOptions"] {
+                    Reflect::set(&data, &JsValue::from_str(key), &JsValue::undefined())
+                        .unwrap_throw();
+                }
             }
 
             let _ = target.dispatch_event(&event);
@@ -412,8 +421,11 @@ pub fn setup_focus_event(win: &Window) {
     };
 
     let keyborg_data = KeyborgData {
+        owners: 1,
+        is_focus_overridden,
+        restore_focus: restore_focus.map(SendWrapper::new),
         focus_in_handler: Arc::new(focus_in_handler.clone()),
-        focus_out_handler: Arc::new(focus_out_handler.clone()),
+        focus_out_handler: Arc::new(focus_out_handler),
     };
 
     KEYBORG_DATA_LIST
@@ -422,7 +434,7 @@ pub fn setup_focus_event(win: &Window) {
         .push(kwin.clone(), keyborg_data);
     let obj = js_sys::Object::new();
     Reflect::set(&obj, &JsValue::from_str("shadowTargets"), &shadow_targets).unwrap_throw();
-    Reflect::set(&kwin, &JsValue::from_str("__keyborgData"), &obj).unwrap_throw();
+    Reflect::set(kwin, &JsValue::from_str(KEYBORG_DATA), &obj).unwrap_throw();
 
     let doc = kwin.document().unwrap_throw();
     let mut event_listener_map = EVENT_LISTENER_MAP.write().unwrap_throw();
@@ -440,10 +452,7 @@ pub fn setup_focus_event(win: &Window) {
     // If keyborg is created with the focus inside shadow root, we need
     // to go through the shadows up to make sure all relevant shadows
     // have focus handlers attached.
-    loop {
-        let Some(el) = &active_element else {
-            break;
-        };
+    while let Some(el) = &active_element {
         let Some(shadow_root) = &el.shadow_root() else {
             break;
         };
@@ -453,14 +462,25 @@ pub fn setup_focus_event(win: &Window) {
     }
 }
 
-/// Removes keyborg event listeners and custom focus override
+/// @returns Whether the calls to `focus()` are intercepted in the window, so that
+/// `isFocusedProgrammatically` is reported in the keyborg focus in events
+pub fn is_programmatic_focus_detected(win: &Window) -> bool {
+    KEYBORG_DATA_LIST
+        .read()
+        .unwrap_throw()
+        .find(win)
+        .is_some_and(|data| data.is_focus_overridden)
+}
+
+/// Removes keyborg event listeners and custom focus override, once each setup of the window
+/// is disposed
 /// @param win The window that stores keyborg focus events
 pub fn dispose_focus_event(win: Window) {
     let kwin = win;
-    let html_element = kwin.get("HTMLElemnt").unwrap_throw();
-    let proto = Reflect::get(&html_element, &JsValue::from_str("prototype")).unwrap_throw();
-    let orig_focus = kwin.get("__keyborgNativeFocus");
-    let keyborg_data = kwin.get("__keyborgData");
+    if KEYBORG_DATA_LIST.write().unwrap_throw().release(&kwin) {
+        return;
+    }
+    let keyborg_data = kwin.get(KEYBORG_DATA);
 
     if let Some(keyborg_data) = keyborg_data {
         let doc = kwin.document().unwrap_throw();
@@ -488,16 +508,56 @@ pub fn dispose_focus_event(win: Window) {
 
         shadow_targets.clear();
 
-        KEYBORG_DATA_LIST.write().unwrap_throw().remove(&kwin);
         Reflect::set(
             &kwin,
-            &JsValue::from_str("__keyborgData"),
+            &JsValue::from_str(KEYBORG_DATA),
             &JsValue::undefined(),
         )
         .unwrap_throw();
     }
 
-    if let Some(orig_focus) = orig_focus {
-        Reflect::set(&proto, &JsValue::from_str("focus"), &orig_focus).unwrap_throw();
+    // Also when the window navigated to another document since it was set up.
+    let data = KEYBORG_DATA_LIST.write().unwrap_throw().remove(&kwin);
+
+    if let Some(restore_focus) = data.and_then(|data| data.restore_focus) {
+        restore_focus.call0(&JsValue::undefined()).unwrap_throw();
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use js_sys::Object;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    fn data() -> KeyborgData {
+        KeyborgData {
+            owners: 1,
+            is_focus_overridden: true,
+            restore_focus: None,
+            focus_in_handler: Arc::new(|_| {}),
+            focus_out_handler: Arc::new(|_| {}),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn a_setup_shared_with_a_frame_outlives_either_owner() {
+        let parent: Window = Object::new().unchecked_into();
+        let child: Window = Object::new().unchecked_into();
+        let mut list = KeyborgDataList::default();
+        list.push(parent.clone(), data());
+
+        // The core of the parent instruments the frame, then the child creates its own core.
+        assert!(!list.acquire(&child));
+        list.push(child.clone(), data());
+        assert!(list.acquire(&child));
+
+        // Dropping the core of the parent keeps the setup of the child.
+        assert!(list.release(&child));
+        assert!(list.find(&child).is_some());
+        assert!(!list.release(&child));
+        assert!(list.remove(&child).is_some());
+
+        assert!(!list.release(&parent));
     }
 }
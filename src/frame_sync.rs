@@ -0,0 +1,487 @@
+use crate::{
+    subscription::{Subscribers, Subscription},
+    Keyborg, KeyborgHandle,
+};
+use gloo_events::EventListener;
+use js_sys::{Object, Reflect};
+use send_wrapper::SendWrapper;
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Weak,
+    },
+};
+use wasm_bindgen::{JsCast, JsValue, UnwrapThrowExt};
+use web_sys::{HtmlIFrameElement, MessageEvent, Window};
+
+/// The version of the `postMessage` protocol, sent in the `keyborg` field of the messages.
+/// Messages of a newer version are ignored.
+pub const FRAME_SYNC_VERSION: u32 = 1;
+
+/// The direction the focus left a frame in, like with Tab or Shift+Tab.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FocusDirection {
+    Forward,
+    Backward,
+}
+
+/// A message of the protocol, posted as `{ keyborg: 1, kind, value }`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FrameMessage {
+    /// `{ kind: "mode", value: "keyboard" | "pointer" }`
+    Mode { is_navigating_with_keyboard: bool },
+    /// `{ kind: "focusout", value: "forward" | "backward" }`
+    FocusOut { direction: FocusDirection },
+    /// `{ kind: "hello", value: "" }`, posted by the child frame when set up, answered by the
+    /// parent with its mode.
+    Hello,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum FrameMessageError {
+    UnsupportedVersion(u32),
+    Unknown { kind: String, value: String },
+}
+
+impl fmt::Display for FrameMessageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported keyborg message version {version}")
+            }
+            Self::Unknown { kind, value } => {
+                write!(f, "unknown keyborg message {kind:?} with value {value:?}")
+            }
+        }
+    }
+}
+
+impl FrameMessage {
+    /// @returns The `kind` and `value` fields
+    pub fn encode(self) -> (&'static str, &'static str) {
+        match self {
+            Self::Mode {
+                is_navigating_with_keyboard: true,
+            } => ("mode", "keyboard"),
+            Self::Mode {
+                is_navigating_with_keyboard: false,
+            } => ("mode", "pointer"),
+            Self::FocusOut {
+                direction: FocusDirection::Forward,
+            } => ("focusout", "forward"),
+            Self::FocusOut {
+                direction: FocusDirection::Backward,
+            } => ("focusout", "backward"),
+            Self::Hello => ("hello", ""),
+        }
+    }
+
+    pub fn decode(version: u32, kind: &str, value: &str) -> Result<Self, FrameMessageError> {
+        if version == 0 || version > FRAME_SYNC_VERSION {
+            return Err(FrameMessageError::UnsupportedVersion(version));
+        }
+
+        match (kind, value) {
+            ("mode", "keyboard") => Ok(Self::Mode {
+                is_navigating_with_keyboard: true,
+            }),
+            ("mode", "pointer") => Ok(Self::Mode {
+                is_navigating_with_keyboard: false,
+            }),
+            ("focusout", "forward") => Ok(Self::FocusOut {
+                direction: FocusDirection::Forward,
+            }),
+            ("focusout", "backward") => Ok(Self::FocusOut {
+                direction: FocusDirection::Backward,
+            }),
+            ("hello", "") => Ok(Self::Hello),
+            _ => Err(FrameMessageError::Unknown {
+                kind: kind.to_string(),
+                value: value.to_string(),
+            }),
+        }
+    }
+
+    fn to_js(self) -> JsValue {
+        let (kind, value) = self.encode();
+        let message = Object::new();
+        for (key, field) in [
+            ("keyborg", JsValue::from(FRAME_SYNC_VERSION)),
+            ("kind", JsValue::from_str(kind)),
+            ("value", JsValue::from_str(value)),
+        ] {
+            Reflect::set(&message, &JsValue::from_str(key), &field).unwrap_throw();
+        }
+        message.into()
+    }
+
+    /// @returns `None` when the data is not a keyborg message
+    fn from_js(data: &JsValue) -> Option<Result<Self, FrameMessageError>> {
+        if !data.is_object() {
+            return None;
+        }
+        let field = |key: &str| Reflect::get(data, &JsValue::from_str(key)).ok();
+        let version = field("keyborg")?.as_f64()?;
+        let kind = field("kind")?.as_string().unwrap_or_default();
+        let value = field("value")?.as_string().unwrap_or_default();
+
+        Some(Self::decode(version as u32, &kind, &value))
+    }
+}
+
+/// The origins the messages are accepted from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct OriginAllowList(Vec<String>);
+
+impl OriginAllowList {
+    pub fn push(&mut self, pattern: impl Into<String>) {
+        let pattern = pattern.into();
+        self.0.push(pattern.trim_end_matches('/').to_string());
+    }
+
+    /// Patterns are exact origins, origins with a wildcard subdomain like
+    /// `https://*.example.com`, or `*` for any origin.
+    pub fn is_allowed(&self, origin: &str) -> bool {
+        self.0.iter().any(|pattern| {
+            if pattern == "*" || pattern == origin {
+                return true;
+            }
+
+            let (Some((scheme, host)), Some((origin_scheme, origin_host))) =
+                (pattern.split_once("://"), origin.split_once("://"))
+            else {
+                return false;
+            };
+            let Some(suffix) = host.strip_prefix('*') else {
+                return false;
+            };
+
+            scheme == origin_scheme
+                && suffix.starts_with('.')
+                && origin_host.len() > suffix.len()
+                && origin_host.ends_with(suffix)
+        })
+    }
+}
+
+/// Where the messages are posted to and accepted from.
+#[derive(Debug, Clone)]
+pub struct FrameSyncOptions {
+    target_origin: String,
+    allowed_origins: OriginAllowList,
+}
+
+impl FrameSyncOptions {
+    /// Messages are posted to the other frame when it has the `target_origin`, and only
+    /// accepted from it.
+    ///
+    /// ```
+    /// # use keyborg::FrameSyncOptions;
+    /// let options = FrameSyncOptions::new("https://embed.example.com")
+    ///     .allow_origin("https://*.example.com");
+    /// ```
+    pub fn new(target_origin: impl Into<String>) -> Self {
+        let target_origin = target_origin.into();
+        let mut allowed_origins = OriginAllowList::default();
+        allowed_origins.push(target_origin.clone());
+
+        Self {
+            target_origin,
+            allowed_origins,
+        }
+    }
+
+    /// Also accepts the messages from the origins matching the pattern, an exact origin, an
+    /// origin with a wildcard subdomain like `https://*.example.com`, or `*`.
+    pub fn allow_origin(mut self, pattern: impl Into<String>) -> Self {
+        self.allowed_origins.push(pattern);
+        self
+    }
+}
+
+enum Peer {
+    Child(SendWrapper<HtmlIFrameElement>),
+    Parent(SendWrapper<Window>),
+}
+
+impl Peer {
+    fn window(&self) -> Option<Window> {
+        match self {
+            // The frame may have navigated, so its window is looked up every time.
+            Self::Child(iframe) => iframe.content_window(),
+            Self::Parent(win) => Some(win.clone().take()),
+        }
+    }
+}
+
+struct FrameSyncInner {
+    peer: Peer,
+    target_origin: String,
+    // Set while applying a received state, so that it is not posted back.
+    applying: AtomicBool,
+}
+
+impl FrameSyncInner {
+    fn post(&self, message: FrameMessage) {
+        if let Some(win) = self.peer.window() {
+            let _ = win.post_message(&message.to_js(), &self.target_origin);
+        }
+    }
+
+    fn is_parent(&self) -> bool {
+        matches!(self.peer, Peer::Child(_))
+    }
+
+    /// Sets the state received from the other frame without posting it back.
+    fn set_from_peer(&self, keyborg: &Keyborg, is_navigating_with_keyboard: bool) {
+        self.applying.store(true, Ordering::SeqCst);
+        keyborg.set_navigating_with_keyboard(is_navigating_with_keyboard);
+        self.applying.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Syncs the keyboard navigation state with a cross-origin frame running keyborg too, and
+/// hands the focus off to it. Stops syncing when dropped.
+pub struct KeyborgFrameSync {
+    inner: Arc<FrameSyncInner>,
+    focus_out_cb: Subscribers<FocusDirection>,
+    _subscription: Subscription,
+    _listener: EventListener,
+}
+
+impl KeyborgFrameSync {
+    /// Parent side, syncing with the frame loaded in `iframe`.
+    pub fn with_child(
+        keyborg: &KeyborgHandle,
+        iframe: &HtmlIFrameElement,
+        options: FrameSyncOptions,
+    ) -> Self {
+        let win = iframe
+            .owner_document()
+            .and_then(|doc| doc.default_view())
+            .unwrap_throw();
+        let peer = Peer::Child(SendWrapper::new(iframe.clone()));
+
+        Self::new(keyborg, &win, peer, options)
+    }
+
+    /// Child side, syncing `win` with the window embedding it.
+    pub fn with_parent(keyborg: &KeyborgHandle, win: &Window, options: FrameSyncOptions) -> Self {
+        let parent = win.parent().ok().flatten().unwrap_throw();
+        let peer = Peer::Parent(SendWrapper::new(parent));
+
+        Self::new(keyborg, win, peer, options)
+    }
+
+    fn new(keyborg: &KeyborgHandle, win: &Window, peer: Peer, options: FrameSyncOptions) -> Self {
+        let FrameSyncOptions {
+            target_origin,
+            allowed_origins,
+        } = options;
+        let inner = Arc::new(FrameSyncInner {
+            peer,
+            target_origin,
+            applying: AtomicBool::new(false),
+        });
+        let focus_out_cb = Subscribers::default();
+
+        let subscription = {
+            let inner = Arc::downgrade(&inner);
+            keyborg.subscribe(move |is_navigating_with_keyboard| {
+                let Some(inner) = inner.upgrade() else {
+                    return;
+                };
+                if !inner.applying.load(Ordering::SeqCst) {
+                    inner.post(FrameMessage::Mode {
+                        is_navigating_with_keyboard,
+                    });
+                }
+            })
+        };
+        // The parent state wins, as a child frame set up or reloaded has not seen the user
+        // navigating yet. The parent also answers the hello of the child, in case the frame
+        // was not loaded yet.
+        if inner.is_parent() {
+            inner.post(FrameMessage::Mode {
+                is_navigating_with_keyboard: keyborg.is_navigating_with_keyboard(),
+            });
+        } else {
+            inner.post(FrameMessage::Hello);
+        }
+
+        let listener = {
+            let keyborg = keyborg.downgrade();
+            let inner = Arc::downgrade(&inner);
+            let focus_out_cb = focus_out_cb.clone();
+            EventListener::new(win, "message", move |event| {
+                let e = event.dyn_ref::<MessageEvent>().unwrap_throw();
+                if !allowed_origins.is_allowed(&e.origin()) {
+                    return;
+                }
+                let Some(inner) = inner.upgrade() else {
+                    return;
+                };
+                let is_from_peer = e.source().is_some_and(|source| {
+                    inner
+                        .peer
+                        .window()
+                        .is_some_and(|win| JsValue::from(win) == JsValue::from(source))
+                });
+                if !is_from_peer {
+                    return;
+                }
+
+                let message = match FrameMessage::from_js(&e.data()) {
+                    Some(Ok(message)) => message,
+                    Some(Err(err)) => {
+                        if cfg!(debug_assertions) {
+                            web_sys::console::warn_1(&JsValue::from(&format!("Keyborg: {err}.")));
+                        }
+                        return;
+                    }
+                    None => return,
+                };
+                apply(&keyborg, &inner, &focus_out_cb, message);
+            })
+        };
+
+        Self {
+            inner,
+            focus_out_cb,
+            _subscription: subscription,
+            _listener: listener,
+        }
+    }
+
+    /// Tells the other frame that the focus left this one in the `direction`, so that it
+    /// continues the keyboard navigation from its side.
+    pub fn hand_off_focus(&self, direction: FocusDirection) {
+        self.inner.post(FrameMessage::FocusOut { direction });
+    }
+
+    /// callback - Called when the other frame handed the focus off to this one, to move the
+    /// focus next to the frame going forward or backward
+    pub fn on_focus_handoff(
+        &self,
+        callback: impl Fn(FocusDirection) + Send + Sync + 'static,
+    ) -> Subscription {
+        self.focus_out_cb.subscribe(callback, false)
+    }
+}
+
+fn apply(
+    keyborg: &Weak<Keyborg>,
+    inner: &FrameSyncInner,
+    focus_out_cb: &Subscribers<FocusDirection>,
+    message: FrameMessage,
+) {
+    let Some(keyborg) = keyborg.upgrade() else {
+        return;
+    };
+
+    match message {
+        FrameMessage::Mode {
+            is_navigating_with_keyboard,
+        } => {
+            inner.set_from_peer(&keyborg, is_navigating_with_keyboard);
+        }
+        FrameMessage::FocusOut { direction } => {
+            // The focus was handed off with the keyboard.
+            inner.set_from_peer(&keyborg, true);
+            focus_out_cb.notify(direction);
+        }
+        FrameMessage::Hello => {
+            if inner.is_parent() {
+                inner.post(FrameMessage::Mode {
+                    is_navigating_with_keyboard: keyborg.is_navigating_with_keyboard(),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allow_list(patterns: &[&str]) -> OriginAllowList {
+        let mut allow_list = OriginAllowList::default();
+        for pattern in patterns {
+            allow_list.push(*pattern);
+        }
+        allow_list
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        for message in [
+            FrameMessage::Mode {
+                is_navigating_with_keyboard: true,
+            },
+            FrameMessage::Mode {
+                is_navigating_with_keyboard: false,
+            },
+            FrameMessage::FocusOut {
+                direction: FocusDirection::Forward,
+            },
+            FrameMessage::FocusOut {
+                direction: FocusDirection::Backward,
+            },
+            FrameMessage::Hello,
+        ] {
+            let (kind, value) = message.encode();
+            assert_eq!(
+                FrameMessage::decode(FRAME_SYNC_VERSION, kind, value),
+                Ok(message)
+            );
+        }
+    }
+
+    #[test]
+    fn unsupported_messages_are_rejected() {
+        assert_eq!(
+            FrameMessage::decode(FRAME_SYNC_VERSION + 1, "mode", "keyboard"),
+            Err(FrameMessageError::UnsupportedVersion(
+                FRAME_SYNC_VERSION + 1
+            ))
+        );
+        assert_eq!(
+            FrameMessage::decode(0, "mode", "keyboard"),
+            Err(FrameMessageError::UnsupportedVersion(0))
+        );
+        assert!(matches!(
+            FrameMessage::decode(FRAME_SYNC_VERSION, "mode", "touch"),
+            Err(FrameMessageError::Unknown { .. })
+        ));
+    }
+
+    #[test]
+    fn exact_origins_are_allowed() {
+        let allow_list = allow_list(&["https://example.com/", "http://localhost:8080"]);
+
+        assert!(allow_list.is_allowed("https://example.com"));
+        assert!(allow_list.is_allowed("http://localhost:8080"));
+        assert!(!allow_list.is_allowed("http://example.com"));
+        assert!(!allow_list.is_allowed("https://example.com.evil.test"));
+        assert!(!allow_list.is_allowed("http://localhost:8081"));
+        assert!(!allow_list.is_allowed("null"));
+    }
+
+    #[test]
+    fn wildcard_subdomains_are_allowed() {
+        let allow_list = allow_list(&["https://*.example.com"]);
+
+        assert!(allow_list.is_allowed("https://embed.example.com"));
+        assert!(allow_list.is_allowed("https://a.b.example.com"));
+        assert!(!allow_list.is_allowed("https://example.com"));
+        assert!(!allow_list.is_allowed("https://evilexample.com"));
+        assert!(!allow_list.is_allowed("http://embed.example.com"));
+    }
+
+    #[test]
+    fn any_origin_is_allowed_with_star() {
+        assert!(allow_list(&["*"]).is_allowed("https://anything.test"));
+        assert!(!OriginAllowList::default().is_allowed("https://anything.test"));
+    }
+}
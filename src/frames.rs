@@ -0,0 +1,73 @@
+use crate::focus_event::{dispose_focus_event, setup_focus_event};
+use gloo_events::EventListener;
+use send_wrapper::SendWrapper;
+use std::sync::{Arc, RwLock};
+use wasm_bindgen::UnwrapThrowExt;
+use web_sys::{Document, HtmlIFrameElement, Window};
+
+/// The same-origin child frames instrumented by a keyborg core, so that the keys pressed and
+/// the focus moved inside them change the keyboard navigation state of the parent.
+#[derive(Clone, Default)]
+pub(crate) struct FrameList(Arc<RwLock<Vec<Frame>>>);
+
+struct Frame {
+    iframe: SendWrapper<HtmlIFrameElement>,
+    // The frame is instrumented again after navigating to another document.
+    doc: SendWrapper<Document>,
+    win: SendWrapper<Window>,
+    _listener_list: Vec<SendWrapper<EventListener>>,
+}
+
+impl Drop for Frame {
+    fn drop(&mut self) {
+        dispose_focus_event(self.win.clone().take());
+    }
+}
+
+impl FrameList {
+    /// Runs `add_listeners` and sets up the keyborg focus events in the frame's window,
+    /// unless the frame is cross-origin or already instrumented.
+    pub fn instrument(
+        &self,
+        iframe: &HtmlIFrameElement,
+        add_listeners: impl FnOnce(&Window) -> Vec<SendWrapper<EventListener>>,
+    ) {
+        // The document of a cross-origin frame is not accessible.
+        let (Some(win), Some(doc)) = (iframe.content_window(), iframe.content_document()) else {
+            return;
+        };
+
+        let stale = {
+            let mut frames = self.0.write().unwrap_throw();
+            if frames
+                .iter()
+                .any(|frame| *frame.iframe == *iframe && *frame.doc == doc)
+            {
+                return;
+            }
+
+            frames
+                .iter()
+                .position(|frame| *frame.iframe == *iframe)
+                .map(|index| frames.remove(index))
+        };
+        drop(stale);
+
+        let listener_list = add_listeners(&win);
+        setup_focus_event(&win);
+
+        self.0.write().unwrap_throw().push(Frame {
+            iframe: SendWrapper::new(iframe.clone()),
+            doc: SendWrapper::new(doc),
+            win: SendWrapper::new(win),
+            _listener_list: listener_list,
+        });
+    }
+
+    /// Removes the listeners and the keyborg focus events from all the frames.
+    pub fn clear(&self) {
+        // The frames are dropped after releasing the lock.
+        let frames = std::mem::take(&mut *self.0.write().unwrap_throw());
+        drop(frames);
+    }
+}
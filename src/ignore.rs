@@ -0,0 +1,35 @@
+use wasm_bindgen::JsCast;
+use web_sys::{Element, Event};
+
+/// Elements with this attribute, and everything inside them, are ignored by keyborg:
+/// keys and pointer events there do not change the keyboard navigation mode.
+pub const KEYBORG_IGNORE: &str = "data-keyborg-ignore";
+
+/// Tells whether an event comes from an opted out subtree.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct IgnoreRule {
+    selector: Option<String>,
+}
+
+impl IgnoreRule {
+    pub fn new(selectors: &[String]) -> Self {
+        Self {
+            selector: (!selectors.is_empty()).then(|| selectors.join(", ")),
+        }
+    }
+
+    /// Checks the composed path, so that the subtrees inside shadow roots are found too.
+    pub fn is_ignored(&self, event: &Event) -> bool {
+        event.composed_path().iter().any(|target| {
+            let Some(el) = target.dyn_ref::<Element>() else {
+                return false;
+            };
+
+            el.has_attribute(KEYBORG_IGNORE)
+                || self
+                    .selector
+                    .as_ref()
+                    .is_some_and(|selector| el.matches(selector).unwrap_or_default())
+        })
+    }
+}
@@ -0,0 +1,70 @@
+use send_wrapper::SendWrapper;
+use std::sync::{Arc, RwLock};
+use wasm_bindgen::UnwrapThrowExt;
+use web_sys::Element;
+
+/// Reflects a state on an element, so that CSS can react to it without a subscriber.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Indicator {
+    /// An empty attribute present while the state is on.
+    Attribute(String),
+    /// A class present while the state is on.
+    Class(String),
+}
+
+impl Indicator {
+    pub fn apply(&self, el: &Element, is_on: bool) {
+        match self {
+            Self::Attribute(name) if is_on => {
+                let _ = el.set_attribute(name, "");
+            }
+            Self::Attribute(name) => {
+                let _ = el.remove_attribute(name);
+            }
+            Self::Class(name) => {
+                let _ = el.class_list().toggle_with_force(name, is_on);
+            }
+        }
+    }
+}
+
+/// Marks the focused element while navigating with keyboard, at most one element at a time.
+#[derive(Clone)]
+pub(crate) struct FocusVisible {
+    indicator: Indicator,
+    element: Arc<RwLock<Option<SendWrapper<Element>>>>,
+}
+
+impl FocusVisible {
+    pub fn new(indicator: Indicator) -> Self {
+        Self {
+            indicator,
+            element: Default::default(),
+        }
+    }
+
+    pub fn show(&self, el: Element) {
+        self.hide();
+        self.indicator.apply(&el, true);
+        *self.element.write().unwrap_throw() = Some(SendWrapper::new(el));
+    }
+
+    pub fn hide(&self) {
+        if let Some(el) = self.element.write().unwrap_throw().take() {
+            self.indicator.apply(&el, false);
+        }
+    }
+
+    /// Hides the mark when it is on `el`, which lost the focus.
+    pub fn hide_on(&self, el: &Element) {
+        let is_marked = self
+            .element
+            .read()
+            .unwrap_throw()
+            .as_ref()
+            .is_some_and(|marked| **marked == *el);
+        if is_marked {
+            self.hide();
+        }
+    }
+}
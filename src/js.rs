@@ -1,5 +1,6 @@
 use js_sys::Object;
-use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+use web_sys::Window;
 
 // WeakSet
 #[wasm_bindgen]
@@ -22,3 +23,220 @@ extern "C" {
     pub fn deref(this: &WeakRef) -> Option<Object>;
 
 }
+
+#[wasm_bindgen(inline_js = r#"
+export function overrideFocus(win, handler) {
+    const prototype = win.HTMLElement.prototype;
+    let next = prototype.focus;
+    let disabled = false;
+    // The JavaScript keyborg library marks its override with `__keyborgNativeFocus`. Its
+    // `__keyborgData` is removed when disposed and replaced when set up again, after which
+    // its override only passes through.
+    const jsData = win.__keyborgData;
+    function isNextJsKeyborg() {
+        const native = next.__keyborgNativeFocus;
+        return !!native && native !== next;
+    }
+    function isOverJsKeyborg() {
+        return isNextJsKeyborg() && jsData !== undefined && win.__keyborgData === jsData;
+    }
+    function unlinkDisposed() {
+        if (isNextJsKeyborg() && !isOverJsKeyborg()) {
+            next = next.__keyborgNativeFocus;
+        }
+    }
+    function focus() {
+        unlinkDisposed();
+        return disabled ? next.apply(this, arguments) : handler(this, next, Array.from(arguments));
+    }
+    // The JavaScript keyborg library skips its override when `__keyborgNativeFocus` is set,
+    // and restores `prototype.focus` to it when disposed. Over a live override of the library,
+    // this one stays installed when the library is disposed, otherwise the library chains its
+    // override to this one. Reading it changes nothing.
+    Object.defineProperty(focus, "__keyborgNativeFocus", {
+        get() {
+            return isOverJsKeyborg() ? focus : undefined;
+        },
+    });
+    try {
+        prototype.focus = focus;
+    } catch {
+        return undefined;
+    }
+    if (prototype.focus !== focus) {
+        return undefined;
+    }
+    return function restore() {
+        if (prototype.focus === focus) {
+            unlinkDisposed();
+            prototype.focus = next;
+        } else {
+            // Another override installed later calls this one, which now only passes through.
+            disabled = true;
+        }
+    };
+}
+"#)]
+extern "C" {
+    /// Replaces `HTMLElement.prototype.focus` of the window with a function calling `handler`
+    /// with `this`, the `focus` it overrides and the arguments, without `eval` so that it works
+    /// under a strict Content Security Policy. The overrides chain, so that they can be
+    /// installed and removed in any order alongside the JavaScript keyborg library.
+    ///
+    /// @returns The function removing the override, or `None` when it is not installed
+    #[wasm_bindgen(js_name = overrideFocus)]
+    pub fn override_focus(win: &Window, handler: &JsValue) -> Option<js_sys::Function>;
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen(inline_js = r#"
+export function stubWindow() {
+    const win = { calls: [] };
+    win.HTMLElement = { prototype: { focus() { win.calls.push("native"); } } };
+    return win;
+}
+
+export function stubHandler(win) {
+    return (element, next, args) => {
+        win.calls.push("rs");
+        return next.apply(element, args);
+    };
+}
+
+// Mirrors `setupFocusEvent` and `disposeFocusEvent` of the JavaScript keyborg library.
+export function setupJsKeyborg(win) {
+    const origFocus = win.HTMLElement.prototype.focus;
+    if (origFocus.__keyborgNativeFocus) {
+        return false;
+    }
+    function focus() {
+        win.calls.push("js");
+        return origFocus.apply(this, arguments);
+    }
+    focus.__keyborgNativeFocus = origFocus;
+    win.HTMLElement.prototype.focus = focus;
+    win.__keyborgData = {};
+    return true;
+}
+
+export function disposeJsKeyborg(win) {
+    const proto = win.HTMLElement.prototype;
+    const origFocus = proto.focus.__keyborgNativeFocus;
+    delete win.__keyborgData;
+    if (origFocus) {
+        proto.focus = origFocus;
+    }
+}
+
+export function focusCalls(win) {
+    win.calls.length = 0;
+    win.HTMLElement.prototype.focus.call({});
+    return win.calls.join(",");
+}
+
+export function isNativeFocus(win) {
+    return win.HTMLElement.prototype.focus.name === "focus" &&
+        !("__keyborgNativeFocus" in win.HTMLElement.prototype.focus);
+}
+"#)]
+    extern "C" {
+        #[wasm_bindgen(js_name = stubWindow)]
+        fn stub_window() -> Window;
+        #[wasm_bindgen(js_name = stubHandler)]
+        fn stub_handler(win: &Window) -> JsValue;
+        #[wasm_bindgen(js_name = setupJsKeyborg)]
+        fn setup_js_keyborg(win: &Window) -> bool;
+        #[wasm_bindgen(js_name = disposeJsKeyborg)]
+        fn dispose_js_keyborg(win: &Window);
+        #[wasm_bindgen(js_name = focusCalls)]
+        fn focus_calls(win: &Window) -> String;
+        #[wasm_bindgen(js_name = isNativeFocus)]
+        fn is_native_focus(win: &Window) -> bool;
+    }
+
+    fn install(win: &Window) -> js_sys::Function {
+        override_focus(win, &stub_handler(win)).unwrap()
+    }
+
+    fn restore(restore: &js_sys::Function) {
+        restore.call0(&JsValue::undefined()).unwrap();
+    }
+
+    #[wasm_bindgen_test]
+    fn installed_before_the_js_keyborg() {
+        for js_first in [true, false] {
+            let win = stub_window();
+            let restore_rs = install(&win);
+            assert!(setup_js_keyborg(&win));
+            assert_eq!(focus_calls(&win), "js,rs,native");
+
+            if js_first {
+                dispose_js_keyborg(&win);
+                assert_eq!(focus_calls(&win), "rs,native");
+                restore(&restore_rs);
+            } else {
+                restore(&restore_rs);
+                assert_eq!(focus_calls(&win), "js,native");
+                dispose_js_keyborg(&win);
+            }
+            assert_eq!(focus_calls(&win), "native");
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn installed_after_the_js_keyborg() {
+        for js_first in [true, false] {
+            let win = stub_window();
+            assert!(setup_js_keyborg(&win));
+            let restore_rs = install(&win);
+            assert_eq!(focus_calls(&win), "rs,js,native");
+
+            if js_first {
+                dispose_js_keyborg(&win);
+                assert_eq!(focus_calls(&win), "rs,native");
+                restore(&restore_rs);
+                assert!(is_native_focus(&win));
+            } else {
+                restore(&restore_rs);
+                assert_eq!(focus_calls(&win), "js,native");
+                dispose_js_keyborg(&win);
+                assert!(is_native_focus(&win));
+            }
+            assert_eq!(focus_calls(&win), "native");
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn reading_the_marker_keeps_the_chain() {
+        let win = stub_window();
+        assert!(setup_js_keyborg(&win));
+        let restore_rs = install(&win);
+
+        // Like a second `setupFocusEvent` of the JavaScript keyborg library.
+        assert!(!setup_js_keyborg(&win));
+        assert!(!setup_js_keyborg(&win));
+        assert_eq!(focus_calls(&win), "rs,js,native");
+
+        restore(&restore_rs);
+        assert_eq!(focus_calls(&win), "js,native");
+    }
+
+    #[wasm_bindgen_test]
+    fn the_js_keyborg_is_set_up_again_after_disposed() {
+        let win = stub_window();
+        assert!(setup_js_keyborg(&win));
+        let restore_rs = install(&win);
+        dispose_js_keyborg(&win);
+
+        assert!(setup_js_keyborg(&win));
+        assert_eq!(focus_calls(&win), "js,rs,native");
+
+        restore(&restore_rs);
+        dispose_js_keyborg(&win);
+        assert_eq!(focus_calls(&win), "native");
+    }
+}
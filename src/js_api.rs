@@ -0,0 +1,181 @@
+//! The JavaScript API of the npm keyborg package, for the apps mixing Rust and JavaScript.
+//!
+//! wasm-bindgen cannot export constants, so the event names of `KEYBORG_FOCUSIN` and
+//! `KEYBORG_FOCUSOUT` are returned by `keyborgFocusInEventName()` and
+//! `keyborgFocusOutEventName()` instead.
+
+use crate::{
+    focus_event::{KEYBORG_FOCUSIN, KEYBORG_FOCUSOUT},
+    key::Key,
+    keyborg::{Keyborg, KeyborgHandle},
+    props::KeyborgProps,
+    subscription::Subscription,
+};
+use js_sys::{Array, Function, Reflect};
+use send_wrapper::SendWrapper;
+use std::cell::RefCell;
+use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+use web_sys::Window;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPES: &str = r#"
+export type KeyborgCallback = (isNavigatingWithKeyboard: boolean) => void;
+
+export interface KeyborgProps {
+    /** The `keyCode`s, or the `key`s, triggering the keyboard navigation mode. */
+    triggerKeys?: (number | string)[];
+    /** The `keyCode`s, or the `key`s, dismissing the keyboard navigation mode. */
+    dismissKeys?: (number | string)[];
+}
+
+export interface KeyborgFocusInEventDetails {
+    relatedTarget?: HTMLElement;
+    isFocusedProgrammatically?: boolean;
+    originalEvent?: FocusEvent;
+    focusOptions?: FocusOptions;
+}
+
+export interface KeyborgFocusOutEventDetails {
+    originalEvent: FocusEvent;
+}
+
+export type KeyborgFocusInEvent = CustomEvent<KeyborgFocusInEventDetails>;
+export type KeyborgFocusOutEvent = CustomEvent<KeyborgFocusOutEventDetails>;
+"#;
+
+/// The name of the keyborg focus in event, `"keyborg:focusin"`, the `KEYBORG_FOCUSIN`
+/// constant of the npm package.
+#[wasm_bindgen(
+    js_name = keyborgFocusInEventName,
+    unchecked_return_type = "\"keyborg:focusin\""
+)]
+pub fn keyborg_focus_in_event_name() -> String {
+    KEYBORG_FOCUSIN.to_string()
+}
+
+/// The name of the keyborg focus out event, `"keyborg:focusout"`, the `KEYBORG_FOCUSOUT`
+/// constant of the npm package.
+#[wasm_bindgen(
+    js_name = keyborgFocusOutEventName,
+    unchecked_return_type = "\"keyborg:focusout\""
+)]
+pub fn keyborg_focus_out_event_name() -> String {
+    KEYBORG_FOCUSOUT.to_string()
+}
+
+/// A keyborg instance created with `createKeyborg`.
+#[wasm_bindgen(js_name = Keyborg)]
+pub struct JsKeyborg {
+    // The methods take `&self`, so that the callbacks can use the instance while notified.
+    keyborg: RefCell<Option<KeyborgHandle>>,
+    callbacks: RefCell<Vec<(Function, Subscription)>>,
+}
+
+#[wasm_bindgen(js_class = Keyborg)]
+impl JsKeyborg {
+    /// @returns Whether the user is navigating with keyboard
+    #[wasm_bindgen(js_name = isNavigatingWithKeyboard)]
+    pub fn is_navigating_with_keyboard(&self) -> bool {
+        self.keyborg
+            .borrow()
+            .as_ref()
+            .is_some_and(|keyborg| keyborg.is_navigating_with_keyboard())
+    }
+
+    /// @param callback - Called when the keyboard navigation state changes
+    pub fn subscribe(
+        &self,
+        #[wasm_bindgen(unchecked_param_type = "KeyborgCallback")] callback: Function,
+    ) {
+        let Some(keyborg) = self.keyborg.borrow().clone() else {
+            return;
+        };
+        let subscription = keyborg.subscribe({
+            let callback = SendWrapper::new(callback.clone());
+            move |is_navigating_with_keyboard| {
+                let _ = callback.call1(
+                    &JsValue::undefined(),
+                    &JsValue::from_bool(is_navigating_with_keyboard),
+                );
+            }
+        });
+        self.callbacks.borrow_mut().push((callback, subscription));
+    }
+
+    /// @param callback - Registered with `subscribe`
+    pub fn unsubscribe(
+        &self,
+        #[wasm_bindgen(unchecked_param_type = "KeyborgCallback")] callback: Function,
+    ) {
+        let removed = {
+            let mut callbacks = self.callbacks.borrow_mut();
+            let index = callbacks.iter().position(|(cb, _)| *cb == callback);
+            index.map(|index| callbacks.remove(index))
+        };
+        if let Some((_, subscription)) = removed {
+            subscription.cancel();
+        }
+    }
+
+    /// Manually sets the keyboard navigation state.
+    #[wasm_bindgen(js_name = setVal)]
+    pub fn set_val(&self, is_navigating_with_keyboard: bool) {
+        // Not borrowed while the callbacks are notified.
+        let keyborg = self.keyborg.borrow().clone();
+        if let Some(keyborg) = keyborg {
+            keyborg.set_navigating_with_keyboard(is_navigating_with_keyboard);
+        }
+    }
+}
+
+impl Drop for JsKeyborg {
+    /// Also called by `free()`, when the instance was not disposed.
+    fn drop(&mut self) {
+        dispose_keyborg(self);
+    }
+}
+
+/// Creates a keyborg instance, sharing the keyborg core of the window with the other instances.
+#[wasm_bindgen(js_name = createKeyborg)]
+pub fn create_keyborg(
+    win: Window,
+    #[wasm_bindgen(unchecked_param_type = "KeyborgProps | undefined")] props: JsValue,
+) -> JsKeyborg {
+    JsKeyborg {
+        keyborg: RefCell::new(Some(Keyborg::create(win, Some(props_from_js(&props))))),
+        callbacks: Default::default(),
+    }
+}
+
+/// Disposes the keyborg instance, the keyborg core is disposed with the last instance of the
+/// window. Disposing an instance again does nothing.
+#[wasm_bindgen(js_name = disposeKeyborg)]
+pub fn dispose_keyborg(instance: &JsKeyborg) {
+    drop(instance.callbacks.take());
+    let keyborg = instance.keyborg.take();
+    if let Some(keyborg) = keyborg {
+        keyborg.dispose();
+    }
+}
+
+fn props_from_js(props: &JsValue) -> KeyborgProps {
+    let keys = |name: &str| -> Vec<Key> {
+        let Ok(keys) = Reflect::get(props, &JsValue::from_str(name)) else {
+            return vec![];
+        };
+        if !Array::is_array(&keys) {
+            return vec![];
+        }
+        Array::from(&keys)
+            .iter()
+            .filter_map(|key| match key.as_f64() {
+                Some(key_code) => Some(Key::KeyCode(key_code as u32)),
+                None => key.as_string().map(Key::Name),
+            })
+            .collect()
+    };
+
+    KeyborgProps::new()
+        .trigger_keys(keys("triggerKeys"))
+        .dismiss_keys(keys("dismissKeys"))
+}
@@ -0,0 +1,352 @@
+use crate::modality::KeyInput;
+use std::{fmt, ops::BitOr, str::FromStr};
+
+/// A key used to trigger or dismiss the keyboard navigation mode.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Key {
+    /// `KeyboardEvent.key`, for example `"Tab"`, `"Escape"` or `"ArrowDown"`.
+    Name(String),
+    /// `KeyboardEvent.code` of a physical key regardless of the keyboard layout,
+    /// for example `"KeyA"` or `"Digit1"`.
+    Code(String),
+    /// The deprecated `KeyboardEvent.keyCode`, kept for compatibility.
+    KeyCode(u32),
+}
+
+impl Key {
+    pub fn name(name: impl Into<String>) -> Self {
+        Self::Name(name.into())
+    }
+
+    pub fn code(code: impl Into<String>) -> Self {
+        Self::Code(code.into())
+    }
+
+    pub(crate) fn matches(&self, input: &KeyInput) -> bool {
+        match self {
+            Self::Name(name) => *name == input.key,
+            Self::Code(code) => *code == input.code,
+            Self::KeyCode(key_code) => *key_code == input.key_code,
+        }
+    }
+}
+
+impl From<&str> for Key {
+    fn from(name: &str) -> Self {
+        Self::name(name)
+    }
+}
+
+impl From<String> for Key {
+    fn from(name: String) -> Self {
+        Self::Name(name)
+    }
+}
+
+impl From<u32> for Key {
+    fn from(key_code: u32) -> Self {
+        Self::KeyCode(key_code)
+    }
+}
+
+/// A set of `KeyboardEvent` modifier keys.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers(u8);
+
+impl Modifiers {
+    pub const NONE: Self = Self(0);
+    pub const SHIFT: Self = Self(1);
+    pub const CTRL: Self = Self(1 << 1);
+    pub const ALT: Self = Self(1 << 2);
+    pub const META: Self = Self(1 << 3);
+    pub const ALL: Self = Self(0b1111);
+
+    pub fn from_event(shift: bool, ctrl: bool, alt: bool, meta: bool) -> Self {
+        [
+            (shift, Self::SHIFT),
+            (ctrl, Self::CTRL),
+            (alt, Self::ALT),
+            (meta, Self::META),
+        ]
+        .into_iter()
+        .filter(|(pressed, _)| *pressed)
+        .fold(Self::NONE, |modifiers, (_, modifier)| modifiers | modifier)
+    }
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    fn difference(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "Shift" => Some(Self::SHIFT),
+            "Ctrl" | "Control" => Some(Self::CTRL),
+            "Alt" | "Option" => Some(Self::ALT),
+            "Meta" | "Cmd" | "Command" | "Win" => Some(Self::META),
+            _ => None,
+        }
+    }
+}
+
+impl BitOr for Modifiers {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum KeyPattern {
+    Any,
+    Arrows,
+    Key(Key),
+}
+
+/// Matches a key together with the modifiers held while pressing it.
+///
+/// Modifiers that are neither required with [`with`](Self::with) nor forbidden with
+/// [`without`](Self::without) are ignored.
+///
+/// ```
+/// # use keyborg::{KeyMatcher, Modifiers};
+/// // Shift+Tab
+/// let shift_tab = KeyMatcher::key("Tab").with(Modifiers::SHIFT);
+/// // Any arrow without modifiers
+/// let arrows = KeyMatcher::arrows().without_modifiers();
+/// // Meta with any key, to be used as an exclusion
+/// let meta: KeyMatcher = "Meta+*".parse().unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyMatcher {
+    pattern: KeyPattern,
+    required: Modifiers,
+    forbidden: Modifiers,
+}
+
+impl KeyMatcher {
+    pub fn key(key: impl Into<Key>) -> Self {
+        Self::new(KeyPattern::Key(key.into()))
+    }
+
+    pub fn any_key() -> Self {
+        Self::new(KeyPattern::Any)
+    }
+
+    /// Any of `ArrowUp`, `ArrowDown`, `ArrowLeft` and `ArrowRight`.
+    pub fn arrows() -> Self {
+        Self::new(KeyPattern::Arrows)
+    }
+
+    fn new(pattern: KeyPattern) -> Self {
+        Self {
+            pattern,
+            required: Modifiers::NONE,
+            forbidden: Modifiers::NONE,
+        }
+    }
+
+    /// Requires the modifiers to be held.
+    pub fn with(mut self, modifiers: Modifiers) -> Self {
+        self.required = self.required | modifiers;
+        self.forbidden = self.forbidden.difference(modifiers);
+        self
+    }
+
+    /// Requires the modifiers not to be held.
+    pub fn without(mut self, modifiers: Modifiers) -> Self {
+        self.forbidden = self.forbidden | modifiers;
+        self.required = self.required.difference(modifiers);
+        self
+    }
+
+    /// Requires all modifiers that are not required to not be held.
+    pub fn without_modifiers(self) -> Self {
+        let forbidden = Modifiers::ALL.difference(self.required);
+        self.without(forbidden)
+    }
+
+    pub(crate) fn matches(&self, input: &KeyInput) -> bool {
+        let is_key_matched = match &self.pattern {
+            KeyPattern::Any => true,
+            KeyPattern::Arrows => matches!(
+                input.key.as_str(),
+                "ArrowUp" | "ArrowDown" | "ArrowLeft" | "ArrowRight"
+            ),
+            KeyPattern::Key(key) => key.matches(input),
+        };
+
+        is_key_matched
+            && input.modifiers.contains(self.required)
+            && !input.modifiers.intersects(self.forbidden)
+    }
+}
+
+impl<K: Into<Key>> From<K> for KeyMatcher {
+    fn from(key: K) -> Self {
+        Self::key(key)
+    }
+}
+
+/// Parses chords like `"Shift+Tab"`, `"Ctrl+Alt+ArrowDown"` or `"Meta+*"`, where `*`
+/// stands for any key. The listed modifiers are required, the others are ignored.
+impl FromStr for KeyMatcher {
+    type Err = ParseKeyMatcherError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (modifiers, key) = match s.strip_suffix("++") {
+            Some(modifiers) => (Some(modifiers), "+"),
+            None if s == "+" => (None, "+"),
+            None => match s.rsplit_once('+') {
+                Some((modifiers, key)) => (Some(modifiers), key),
+                None => (None, s),
+            },
+        };
+
+        if key.is_empty() {
+            return Err(ParseKeyMatcherError(s.to_string()));
+        }
+
+        let matcher = if key == "*" {
+            Self::any_key()
+        } else {
+            Self::key(key)
+        };
+
+        modifiers
+            .into_iter()
+            .flat_map(|modifiers| modifiers.split('+'))
+            .try_fold(matcher, |matcher, name| {
+                Modifiers::parse(name)
+                    .map(|modifier| matcher.with(modifier))
+                    .ok_or_else(|| ParseKeyMatcherError(s.to_string()))
+            })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseKeyMatcherError(String);
+
+impl fmt::Display for ParseKeyMatcherError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid key chord: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseKeyMatcherError {}
+
+/// Matchers to include and exclude keys.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct KeyRule {
+    include: Option<Vec<KeyMatcher>>,
+    exclude: Vec<KeyMatcher>,
+}
+
+impl KeyRule {
+    pub fn include(&mut self, matcher: KeyMatcher) {
+        self.include.get_or_insert_with(Vec::new).push(matcher);
+    }
+
+    pub fn exclude(&mut self, matcher: KeyMatcher) {
+        self.exclude.push(matcher);
+    }
+
+    pub fn is_excluded(&self, input: &KeyInput) -> bool {
+        self.exclude.iter().any(|matcher| matcher.matches(input))
+    }
+
+    /// @returns whether the key is not excluded and is included, or `default` when there
+    /// are no included keys
+    pub fn matches(&self, input: &KeyInput, default: bool) -> bool {
+        !self.is_excluded(input)
+            && self
+                .include
+                .as_ref()
+                .map_or(default, |include| include.iter().any(|m| m.matches(input)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(key: &str, modifiers: Modifiers) -> KeyInput {
+        KeyInput {
+            key: key.to_string(),
+            modifiers,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn matches_name_code_and_key_code() {
+        let input = KeyInput {
+            key: "q".to_string(),
+            code: "KeyA".to_string(),
+            key_code: 81,
+            ..Default::default()
+        };
+
+        assert!(Key::from("q").matches(&input));
+        assert!(!Key::from("a").matches(&input));
+        assert!(Key::code("KeyA").matches(&input));
+        assert!(!Key::code("KeyQ").matches(&input));
+        assert!(Key::from(81).matches(&input));
+    }
+
+    #[test]
+    fn chords_require_and_forbid_modifiers() {
+        let shift_tab = KeyMatcher::key("Tab").with(Modifiers::SHIFT);
+        assert!(shift_tab.matches(&input("Tab", Modifiers::SHIFT)));
+        assert!(shift_tab.matches(&input("Tab", Modifiers::SHIFT | Modifiers::CTRL)));
+        assert!(!shift_tab.matches(&input("Tab", Modifiers::NONE)));
+
+        let arrows = KeyMatcher::arrows().without_modifiers();
+        assert!(arrows.matches(&input("ArrowLeft", Modifiers::NONE)));
+        assert!(!arrows.matches(&input("ArrowLeft", Modifiers::ALT)));
+        assert!(!arrows.matches(&input("Home", Modifiers::NONE)));
+
+        let tab = KeyMatcher::from("Tab");
+        assert!(tab.matches(&input("Tab", Modifiers::SHIFT)));
+    }
+
+    #[test]
+    fn parses_chords() {
+        assert_eq!(
+            "Shift+Tab".parse(),
+            Ok(KeyMatcher::key("Tab").with(Modifiers::SHIFT))
+        );
+        assert_eq!(
+            "Cmd+*".parse(),
+            Ok(KeyMatcher::any_key().with(Modifiers::META))
+        );
+        assert_eq!(
+            "Ctrl++".parse(),
+            Ok(KeyMatcher::key("+").with(Modifiers::CTRL))
+        );
+        assert_eq!("+".parse(), Ok(KeyMatcher::key("+")));
+        assert!("Hyper+a".parse::<KeyMatcher>().is_err());
+        assert!("Shift+".parse::<KeyMatcher>().is_err());
+    }
+
+    #[test]
+    fn rule_exclusions_win() {
+        let mut rule = KeyRule::default();
+        rule.exclude("Meta+*".parse().unwrap());
+
+        assert!(rule.matches(&input("z", Modifiers::NONE), true));
+        assert!(!rule.matches(&input("z", Modifiers::META), true));
+
+        rule.include(KeyMatcher::arrows());
+        assert!(!rule.matches(&input("z", Modifiers::NONE), true));
+        assert!(rule.matches(&input("ArrowUp", Modifiers::SHIFT), true));
+    }
+}
@@ -1,506 +1,824 @@
-use crate::focus_event::{dispose_focus_event, setup_focus_event, KEYBORG_FOCUSIN};
+use crate::{
+    compat::{find_js_core, is_js_navigating, JsAdapter, JsRef, JsSync},
+    editable::{deep_active_element, is_editable_active_element, EditableClassifier},
+    events::{on_keyborg_focus_in, on_keyborg_focus_out, KeyborgFocusInEvent},
+    focus_event::{dispose_focus_event, is_programmatic_focus_detected, setup_focus_event},
+    frames::FrameList,
+    ignore::IgnoreRule,
+    indicator::{FocusVisible, Indicator},
+    key::Modifiers,
+    modality::{
+        InputModality, KeyInput, KeyborgTimings, ModalityEffect, ModalityEvent, ModalityMachine,
+        PointerInput, PointerType,
+    },
+    props::KeyborgProps,
+    registry::{CoreRegistry, Released},
+    stream::{KeyborgChanges, WaitUntil},
+    subscription::{Subscribers, Subscription},
+};
 use gloo_events::{EventListener, EventListenerOptions};
+use gloo_timers::callback::Timeout;
 use js_sys::Reflect;
 use send_wrapper::SendWrapper;
 use std::{
-    collections::{HashMap, HashSet},
-    sync::{Arc, OnceLock, RwLock},
+    ops::Deref,
+    sync::{Arc, LazyLock, OnceLock, RwLock, Weak},
 };
-use wasm_bindgen::{prelude::Closure, JsValue};
+use wasm_bindgen::JsValue;
 use web_sys::{
     wasm_bindgen::{JsCast, UnwrapThrowExt},
-    Event, HtmlElement, KeyboardEvent, Window,
+    Document, Event, HtmlIFrameElement, KeyboardEvent, Window,
 };
 
 static LAST_ID: OnceLock<RwLock<usize>> = OnceLock::new();
-static KEYBORG_MAP: OnceLock<RwLock<Option<KeyborgMap>>> = OnceLock::new();
-
-// When a key from dismiss_keys is pressed and the focus is not moved
-// during DISMISS_TIMEOUT time, dismiss the keyboard navigation mode.
-const DISMISS_TIMEOUT: i32 = 500;
+static KEYBORG_MAP: LazyLock<RwLock<KeyborgMap>> = LazyLock::new(Default::default);
 
-struct KeyborgMap {
-    core: Arc<KeyborgCore>,
-    refs: HashMap<String, Arc<RwLock<Keyborg>>>,
-}
+/// The keyborg cores, one per window, each with the instances using it.
+type KeyborgMap = CoreRegistry<SendWrapper<Window>, KeyborgCore, Weak<Keyborg>>;
 
 impl KeyborgMap {
-    pub fn new(core: Arc<KeyborgCore>) -> Self {
-        Self {
-            core,
-            refs: Default::default(),
+    /// Updates all keyborg instances of the window with the keyboard navigation state
+    fn update(win: &Window, is_navigating_with_keyboard: bool) {
+        for cb in Self::collect(win, |keyborg| keyborg.cb.clone()) {
+            cb.notify(is_navigating_with_keyboard);
         }
     }
+
+    /// Updates all keyborg instances of the window with the input modality
+    fn update_modality(win: &Window, modality: InputModality) {
+        for cb in Self::collect(win, |keyborg| keyborg.modality_cb.clone()) {
+            cb.notify(modality);
+        }
+    }
+
+    /// The callbacks are called after releasing the lock, so that they can use keyborg.
+    fn collect<T>(win: &Window, f: impl Fn(&Keyborg) -> T) -> Vec<T> {
+        let keyborgs = KEYBORG_MAP
+            .read()
+            .unwrap_throw()
+            .refs(win)
+            .filter_map(Weak::upgrade)
+            .collect::<Vec<_>>();
+
+        keyborgs.iter().map(|keyborg| f(keyborg)).collect()
+    }
 }
 
-struct IsNavigatingWithKeyboard {
-    do_not_use: bool,
+/// A pending timeout of the driver, cancelled when dropped.
+type Timer = Arc<RwLock<Option<SendWrapper<Timeout>>>>;
+
+/// Feeds the DOM events into the [`ModalityMachine`] and performs its effects.
+#[derive(Clone)]
+struct ModalityDriver {
+    win: SendWrapper<Window>,
+    machine: Arc<RwLock<ModalityMachine>>,
+    is_mouse_or_touch_used_timer: Timer,
+    dismiss_timer: Timer,
+    tab_timer: Timer,
+    navigating_indicators: Arc<[Indicator]>,
+    focus_visible: Option<FocusVisible>,
+    js_sync: Option<JsSync>,
 }
 
-impl IsNavigatingWithKeyboard {
-    pub fn new() -> Self {
-        Self { do_not_use: false }
+impl ModalityDriver {
+    fn dispatch(&self, event: ModalityEvent) {
+        self.dispatch_with(event, None);
     }
 
-    pub fn get(&self) -> bool {
-        self.do_not_use
+    fn dispatch_key(&self, e: &KeyboardEvent, key: KeyInput) {
+        self.dispatch_with(ModalityEvent::KeyDown(key), Some(e));
     }
 
-    pub fn set(&mut self, val: bool) {
-        if self.do_not_use != val {
-            self.do_not_use = val;
-            self.update();
-        }
-    }
+    fn dispatch_with(&self, event: ModalityEvent, key_event: Option<&KeyboardEvent>) {
+        let effects = self.machine.write().unwrap_throw().handle(event);
 
-    /// Updates all keyborg instances with the keyboard navigation state
-    fn update(&self) {
-        if let Some(keyborg_map) = KEYBORG_MAP.get() {
-            let keyborg_map = keyborg_map.read().unwrap_throw();
-            if let Some(keyborg_map) = keyborg_map.as_ref() {
-                for keyborg in keyborg_map.refs.values() {
-                    let keyborg = keyborg.read().unwrap_throw();
-                    keyborg.update(self.do_not_use)
+        for effect in effects {
+            match effect {
+                ModalityEffect::ModalityChanged(modality) => {
+                    KeyborgMap::update_modality(&self.win, modality)
+                }
+                ModalityEffect::Changed(is_navigating_with_keyboard) => {
+                    self.reflect_navigating(is_navigating_with_keyboard);
+                    self.reflect_focus_visible(is_navigating_with_keyboard);
+                    if let Some(js_sync) = &self.js_sync {
+                        js_sync.update(&self.win, is_navigating_with_keyboard);
+                    }
+                    KeyborgMap::update(&self.win, is_navigating_with_keyboard)
+                }
+                ModalityEffect::StartPointerTimer(timeout) => {
+                    self.start_timer(&self.is_mouse_or_touch_used_timer, timeout, || {
+                        ModalityEvent::PointerTimerFired
+                    });
+                }
+                ModalityEffect::StartDismissTimer(timeout) => {
+                    let was = deep_active_element(&self.win.document().unwrap_throw());
+                    let win = self.win.clone();
+                    self.start_timer(&self.dismiss_timer, timeout, move || {
+                        let cur = deep_active_element(&win.document().unwrap_throw());
+                        ModalityEvent::DismissTimerFired {
+                            focus_moved: was.is_none() || was != cur,
+                        }
+                    });
+                }
+                ModalityEffect::VerifyTab => {
+                    let Some(e) = key_event.cloned() else {
+                        continue;
+                    };
+                    let was = deep_active_element(&self.win.document().unwrap_throw());
+                    let win = self.win.clone();
+                    // Checking after the event has been dispatched rather than in a bubble
+                    // listener, which the app could skip by stopping the propagation.
+                    self.start_timer(&self.tab_timer, 0, move || {
+                        let cur = deep_active_element(&win.document().unwrap_throw());
+                        ModalityEvent::TabVerified {
+                            navigated: !e.default_prevented() && was != cur,
+                        }
+                    });
                 }
             }
         }
     }
-}
 
-#[derive(Debug, Clone)]
-pub struct KeyborgProps {
-    // Keys to be used to trigger keyboard navigation mode. By default, any key will trigger
-    // it. Could be limited to, for example, just Tab (or Tab and arrow keys).
-    trigger_keys: Option<Vec<u32>>,
-    // Keys to be used to dismiss keyboard navigation mode using keyboard (in addition to
-    // mouse clicks which dismiss it). For example, Esc could be used to dismiss.
-    dismiss_keys: Option<Vec<u32>>,
+    /// Replaces the pending `timer` with a new one dispatching the event returned by `on_timeout`.
+    fn start_timer(
+        &self,
+        timer: &Timer,
+        timeout: i32,
+        on_timeout: impl FnOnce() -> ModalityEvent + 'static,
+    ) {
+        // The fired timeout stays in `timer` until it is replaced or cleared, as it cannot be
+        // dropped while its callback runs. Dropping it cancels it and frees the callback.
+        let timeout = Timeout::new(timeout.max(0) as u32, {
+            let driver = self.clone();
+            move || driver.dispatch(on_timeout())
+        });
+        let replaced = timer
+            .write()
+            .unwrap_throw()
+            .replace(SendWrapper::new(timeout));
+        drop(replaced);
+    }
+
+    fn reflect_navigating(&self, is_navigating_with_keyboard: bool) {
+        if self.navigating_indicators.is_empty() {
+            return;
+        }
+
+        let Some(root) = self.win.document().and_then(|doc| doc.document_element()) else {
+            return;
+        };
+        for indicator in self.navigating_indicators.iter() {
+            indicator.apply(&root, is_navigating_with_keyboard);
+        }
+    }
+
+    fn reflect_focus_visible(&self, is_navigating_with_keyboard: bool) {
+        let Some(focus_visible) = &self.focus_visible else {
+            return;
+        };
+
+        let focused = is_navigating_with_keyboard
+            .then(|| deep_active_element(&self.win.document().unwrap_throw()))
+            .flatten();
+        match focused {
+            Some(el) => focus_visible.show(el),
+            None => focus_visible.hide(),
+        }
+    }
+
+    fn clear_timers(&self) {
+        for timer in [
+            &self.is_mouse_or_touch_used_timer,
+            &self.dismiss_timer,
+            &self.tab_timer,
+        ] {
+            // Dropped after releasing the lock.
+            let timeout = timer.write().unwrap_throw().take();
+            drop(timeout);
+        }
+    }
 }
 
 struct KeyborgCore {
-    id: String,
     win: SendWrapper<Window>,
-
-    is_mouse_or_touch_used_timer: Arc<RwLock<Option<i32>>>,
-    dismiss_timer: Arc<RwLock<Option<i32>>>,
-    trigger_keys: Arc<Option<HashSet<u32>>>,
-    dismiss_keys: Arc<Option<HashSet<u32>>>,
-    is_navigating_with_keyboard: Arc<RwLock<IsNavigatingWithKeyboard>>,
+    driver: ModalityDriver,
+    frames: FrameList,
+    // Whether the JavaScript keyborg library tracks the events in the window instead.
+    is_attached: bool,
 
     _listener_list: Vec<SendWrapper<EventListener>>,
+    _js_ref: Option<JsRef>,
+    _js_adapter: Option<JsAdapter>,
+}
+
+/// What the listeners added to a window, or to the instrumented child frames, need.
+#[derive(Clone)]
+struct ListenerConfig {
+    use_pointer_events: bool,
+    ignore_rule: IgnoreRule,
+    editable_classifiers: Arc<[EditableClassifier]>,
+    instrument_frames: bool,
 }
 
 impl KeyborgCore {
     pub fn new(win: Window, props: Option<KeyborgProps>) -> Self {
-        let last_id = LAST_ID.get_or_init(Default::default);
-        let id = *last_id.read().unwrap_throw() + 1;
-        *last_id.write().unwrap_throw() = id;
-
-        let mut dismiss_keys = None::<HashSet<u32>>;
-        let mut trigger_keys = None::<HashSet<u32>>;
-
-        if let Some(props) = props {
-            if let Some(keys) = props.trigger_keys {
-                if !keys.is_empty() {
-                    trigger_keys = Some(HashSet::from_iter(keys));
-                }
-            }
-
-            if let Some(keys) = props.dismiss_keys {
-                if !keys.is_empty() {
-                    dismiss_keys = Some(HashSet::from_iter(keys));
+        let props = props.unwrap_or_default();
+        let machine = ModalityMachine::new(&props);
+        let js_core = props.js_compat.then(|| find_js_core(&win)).flatten();
+
+        let driver = ModalityDriver {
+            win: SendWrapper::new(win.clone()),
+            machine: Arc::new(RwLock::new(machine)),
+            is_mouse_or_touch_used_timer: Default::default(),
+            dismiss_timer: Default::default(),
+            tab_timer: Default::default(),
+            navigating_indicators: props.navigating_indicators.clone().into(),
+            focus_visible: props.focus_visible_indicator.clone().map(FocusVisible::new),
+            js_sync: props.js_compat.then(|| match &js_core {
+                Some(js_core) => JsSync::Attached(SendWrapper::new(js_core.clone())),
+                None => JsSync::Exposed,
+            }),
+        };
+        let config = ListenerConfig {
+            use_pointer_events: props.use_pointer_events,
+            ignore_rule: IgnoreRule::new(&props.ignore_selectors),
+            editable_classifiers: props.editable_classifiers.into(),
+            instrument_frames: props.instrument_frames,
+        };
+        let frames = FrameList::default();
+
+        if let Some(js_core) = js_core {
+            // The JavaScript keyborg core already handles the keys, the pointers and the focus,
+            // only its state and its keyborg focus events are followed.
+            let listener_list = add_focus_listeners(&win.document().unwrap_throw(), &driver, true);
+            let js_ref = JsRef::new(&win, format!("rs{}", next_id()), {
+                let driver = driver.clone();
+                move |is_navigating_with_keyboard| {
+                    driver.dispatch(ModalityEvent::Set(is_navigating_with_keyboard))
                 }
-            }
+            });
+            driver.dispatch(ModalityEvent::Set(is_js_navigating(&js_core)));
+
+            return Self {
+                win: SendWrapper::new(win),
+                driver,
+                frames,
+                is_attached: true,
+                _listener_list: listener_list,
+                _js_ref: js_ref,
+                _js_adapter: None,
+            };
         }
-        let dismiss_keys = Arc::new(dismiss_keys);
-        let trigger_keys = Arc::new(trigger_keys);
-
-        let is_mouse_or_touch_used_timer = Arc::new(RwLock::new(None::<i32>));
-        let dismiss_timer = Arc::new(RwLock::new(None::<i32>));
-        let is_navigating_with_keyboard = Arc::new(RwLock::new(IsNavigatingWithKeyboard::new()));
-        let mut listener_list = vec![];
 
-        let doc = win.document().unwrap_throw();
+        let listener_list = add_listeners(&win, &driver, &config, &frames);
 
-        let on_focus_in = {
-            let is_mouse_or_touch_used_timer = is_mouse_or_touch_used_timer.clone();
-            let is_navigating_with_keyboard = is_navigating_with_keyboard.clone();
-            move |event: &Event| {
-                let e = event.dyn_ref::<web_sys::CustomEvent>().unwrap_throw();
+        setup_focus_event(&win);
 
-                // When the focus is moved not programmatically and without keydown events,
-                // it is likely that the focus is moved by screen reader (as it might swallow
-                // the events when the screen reader shortcuts are used). The screen reader
-                // usage is keyboard navigation.
+        let js_adapter = props.js_compat.then(|| {
+            let machine = driver.machine.clone();
+            let driver = driver.clone();
+            JsAdapter::new(
+                &win,
+                move || machine.read().unwrap_throw().is_navigating_with_keyboard(),
+                move |is_navigating_with_keyboard| {
+                    driver.dispatch(ModalityEvent::Set(is_navigating_with_keyboard))
+                },
+            )
+        });
 
-                if is_mouse_or_touch_used_timer.read().unwrap_throw().is_some() {
-                    // There was a mouse or touch event recently.
-                    return;
-                }
+        Self {
+            win: SendWrapper::new(win),
+            driver,
+            frames,
+            is_attached: false,
+            _listener_list: listener_list,
+            _js_ref: None,
+            _js_adapter: js_adapter,
+        }
+    }
 
-                if is_navigating_with_keyboard.read().unwrap_throw().get() {
-                    return;
-                }
+    fn is_navigating_with_keyboard(&self) -> bool {
+        self.driver
+            .machine
+            .read()
+            .unwrap_throw()
+            .is_navigating_with_keyboard()
+    }
 
-                // KeyborgFocusInEventDetails
-                let details = e.detail();
+    fn modality(&self) -> InputModality {
+        self.driver.machine.read().unwrap_throw().modality()
+    }
+}
 
-                let Some(details) = details.dyn_ref::<js_sys::Object>() else {
-                    return;
-                };
+/// Feeds the events of `win` into the driver.
+fn add_listeners(
+    win: &Window,
+    driver: &ModalityDriver,
+    config: &ListenerConfig,
+    frames: &FrameList,
+) -> Vec<SendWrapper<EventListener>> {
+    let use_pointer_events = config.use_pointer_events
+        && Reflect::has(win, &JsValue::from("PointerEvent")).unwrap_throw();
+    let doc = win.document().unwrap_throw();
+    let mut listener_list = add_focus_listeners(&doc, driver, false);
+
+    let ignore_rule = config.ignore_rule.clone();
+
+    if use_pointer_events {
+        let on_pointer = {
+            let driver = driver.clone();
+            let ignore_rule = ignore_rule.clone();
+            move |event: &Event| {
+                let e = event.dyn_ref::<web_sys::PointerEvent>().unwrap_throw();
+                let pointer_type = PointerType::from_pointer_type(&e.pointer_type());
 
-                if !Reflect::has(&details, &JsValue::from("relatedTarget")).unwrap_throw() {
+                if ignore_rule.is_ignored(event) {
+                    driver.dispatch(ModalityEvent::IgnoredPointer(pointer_type));
                     return;
                 }
 
-                let is_focused_programmatically =
-                    Reflect::get(&details, &JsValue::from("isFocusedProgrammatically"))
-                        .unwrap_throw();
-
-                if is_focused_programmatically.as_bool().unwrap_or_default()
-                    || is_focused_programmatically.is_undefined()
-                {
-                    // The element is focused programmatically, or the programmatic focus detection
-                    // is not working.
+                if pointer_type == PointerType::Mouse && e.type_() != "pointerdown" {
+                    // Only touch and pen keep the indication of their usage fresh
+                    // until the interaction ends.
                     return;
                 }
 
-                is_navigating_with_keyboard.write().unwrap_throw().set(true);
+                driver.dispatch(ModalityEvent::Pointer(pointer_input(e, pointer_type)));
             }
         };
         let options = EventListenerOptions::run_in_capture_phase();
-        let listener = EventListener::new_with_options(&doc, KEYBORG_FOCUSIN, options, on_focus_in);
+        let listener =
+            EventListener::new_with_options(&doc, "pointerdown", options, on_pointer.clone());
         listener_list.push(SendWrapper::new(listener));
 
-        let on_mouse_or_touch = {
-            let is_mouse_or_touch_used_timer = is_mouse_or_touch_used_timer.clone();
-            let is_navigating_with_keyboard = is_navigating_with_keyboard.clone();
-            let win = win.clone();
-            move |_: &Event| {
-                if let Some(timer) = is_mouse_or_touch_used_timer.read().unwrap_throw().clone() {
-                    win.clear_timeout_with_handle(timer);
-                }
-
-                let closure = Closure::once({
-                    let is_mouse_or_touch_used_timer = is_mouse_or_touch_used_timer.clone();
-                    move || {
-                        is_mouse_or_touch_used_timer.write().unwrap_throw().take();
-                    }
-                });
-
-                let id = win
-                    .set_timeout_with_callback_and_timeout_and_arguments_0(
-                        closure.as_ref().unchecked_ref::<js_sys::Function>(),
-                        1000,
-                    )
-                    .unwrap_throw();
-                *is_mouse_or_touch_used_timer.write().unwrap_throw() = Some(id);
-                // Keeping the indication of mouse or touch usage for some time.
-
-                is_navigating_with_keyboard
-                    .write()
-                    .unwrap_throw()
-                    .set(false);
-            }
-        };
-
+        for event_type in ["pointerup", "pointercancel"] {
+            let options = EventListenerOptions::run_in_capture_phase();
+            let listener =
+                EventListener::new_with_options(win, event_type, options, on_pointer.clone());
+            listener_list.push(SendWrapper::new(listener));
+        }
+    } else {
         let on_mouse_down = {
-            let on_mouse_or_touch = on_mouse_or_touch.clone();
+            let driver = driver.clone();
+            let ignore_rule = ignore_rule.clone();
             move |event: &Event| {
-                let e = event.dyn_ref::<web_sys::MouseEvent>().unwrap_throw();
-                if e.buttons() == 0
-                    || (e.client_x() == 0
-                        && e.client_y() == 0
-                        && e.screen_x() == 0
-                        && e.screen_y() == 0)
-                {
-                    // This is most likely an event triggered by the screen reader to perform
-                    // an action on an element, do not dismiss the keyboard navigation mode.
+                if ignore_rule.is_ignored(event) {
+                    driver.dispatch(ModalityEvent::IgnoredPointer(PointerType::Mouse));
                     return;
                 }
 
-                on_mouse_or_touch(event);
+                let e = event.dyn_ref::<web_sys::MouseEvent>().unwrap_throw();
+                driver.dispatch(ModalityEvent::Pointer(pointer_input(e, PointerType::Mouse)));
             }
         };
         let options = EventListenerOptions::run_in_capture_phase();
         let listener = EventListener::new_with_options(&doc, "mousedown", options, on_mouse_down);
         listener_list.push(SendWrapper::new(listener));
 
-        let should_dismiss_keyboard_navigation = {
-            let dismiss_keys = dismiss_keys.clone();
-            move |e: &KeyboardEvent| {
-                dismiss_keys
-                    .as_ref()
-                    .clone()
-                    .is_some_and(|keys| keys.contains(&e.key_code()))
-            }
-        };
-
-        let schedule_dismiss = {
-            let win = win.clone();
-            let dismiss_timer = dismiss_timer.clone();
-            let is_navigating_with_keyboard = is_navigating_with_keyboard.clone();
-            move || {
-                if let Some(timer) = dismiss_timer.read().unwrap_throw().clone() {
-                    win.clear_timeout_with_handle(timer);
-                }
-
-                let was = win.document().unwrap_throw().active_element();
-
-                let closure = Closure::once({
-                    let dismiss_timer = dismiss_timer.clone();
-                    let is_navigating_with_keyboard = is_navigating_with_keyboard.clone();
-                    let win = win.clone();
-                    move || {
-                        dismiss_timer.write().unwrap_throw().take();
-                        let cur = win.document().unwrap_throw().active_element();
-
-                        let Some(was) = was else {
-                            return;
-                        };
-
-                        let Some(cur) = cur else {
-                            return;
-                        };
-
-                        if was == cur {
-                            // Esc was pressed, currently focused element hasn't changed.
-                            // Just dismiss the keyboard navigation mode.
-                            is_navigating_with_keyboard
-                                .write()
-                                .unwrap_throw()
-                                .set(false);
-                        }
+        for event_type in ["touchstart", "touchend", "touchcancel"] {
+            let driver = driver.clone();
+            let ignore_rule = ignore_rule.clone();
+            let options = EventListenerOptions::run_in_capture_phase();
+            let listener =
+                EventListener::new_with_options(win, event_type, options, move |event| {
+                    if ignore_rule.is_ignored(event) {
+                        driver.dispatch(ModalityEvent::IgnoredPointer(PointerType::Touch))
+                    } else {
+                        driver.dispatch(ModalityEvent::Pointer(PointerInput::touch()))
                     }
                 });
+            listener_list.push(SendWrapper::new(listener));
+        }
+    }
 
-                let id = win
-                    .set_timeout_with_callback_and_timeout_and_arguments_0(
-                        closure.as_ref().unchecked_ref::<js_sys::Function>(),
-                        DISMISS_TIMEOUT,
-                    )
-                    .unwrap_throw();
-                *dismiss_timer.write().unwrap_throw() = Some(id);
+    let on_key_down = {
+        let driver = driver.clone();
+        let win = win.clone();
+        let editable_classifiers = config.editable_classifiers.clone();
+        move |event: &Event| {
+            if ignore_rule.is_ignored(event) {
+                return;
             }
-        };
 
-        // @returns whether the keyboard event should trigger keyboard navigation mode
-        let should_trigger_keyboard_navigation = {
-            let win = win.clone();
-            let trigger_keys = trigger_keys.clone();
-            move |e: &KeyboardEvent| {
-                // TODO Some rich text fields can allow Tab key for indentation so it doesn't
-                // need to be a navigation key. If there is a bug regarding that we should revisit
-                if e.key() == "Tab" {
-                    return true;
-                }
-
-                let active_element = win.document().unwrap_throw().active_element();
-
-                let is_trigger_key = trigger_keys
-                    .as_ref()
-                    .clone()
-                    .map_or(true, |keys| keys.contains(&e.key_code()));
-
-                let is_editable = if let Some(Ok(active_element)) =
-                    active_element.map(|el| el.dyn_into::<HtmlElement>())
-                {
-                    if ["INPUT".to_string(), "TEXTAREA".to_string()]
-                        .contains(&active_element.tag_name())
-                    {
-                        true
-                    } else if active_element.is_content_editable() {
-                        true
-                    } else {
-                        false
-                    }
-                } else {
-                    false
+            let e = event.dyn_ref::<web_sys::KeyboardEvent>().unwrap_throw();
+
+            let is_editable =
+                is_editable_active_element(&win.document().unwrap_throw(), &editable_classifiers);
+
+            driver.dispatch_key(
+                e,
+                KeyInput {
+                    key: e.key(),
+                    code: e.code(),
+                    key_code: e.key_code(),
+                    modifiers: Modifiers::from_event(
+                        e.shift_key(),
+                        e.ctrl_key(),
+                        e.alt_key(),
+                        e.meta_key(),
+                    ),
+                    is_editable,
+                },
+            );
+        }
+    };
+    let options = EventListenerOptions::run_in_capture_phase();
+    let listener = EventListener::new_with_options(win, "keydown", options, on_key_down);
+    listener_list.push(SendWrapper::new(listener));
+
+    if config.instrument_frames {
+        let instrument = {
+            let driver = driver.clone();
+            let config = config.clone();
+            let frames = frames.clone();
+            move |iframe: &HtmlIFrameElement| {
+                let Some(child) = iframe.content_window() else {
+                    return;
                 };
+                // A window with its own core is left to it.
+                if KEYBORG_MAP.read().unwrap_throw().core(&child).is_some() {
+                    return;
+                }
 
-                is_trigger_key && !is_editable
+                frames.instrument(iframe, |child| {
+                    add_listeners(child, &driver, &config, &frames)
+                });
             }
         };
 
-        let on_key_down = {
-            let is_navigating_with_keyboard = is_navigating_with_keyboard.clone();
-
-            move |event: &Event| {
-                let e = event.dyn_ref::<web_sys::KeyboardEvent>().unwrap_throw();
+        // The focus is already inside a frame.
+        if let Some(iframe) = doc
+            .active_element()
+            .and_then(|el| el.dyn_into::<HtmlIFrameElement>().ok())
+        {
+            instrument(&iframe);
+        }
 
-                if is_navigating_with_keyboard.read().unwrap_throw().get() {
-                    if should_dismiss_keyboard_navigation(e) {
-                        schedule_dismiss();
-                    }
-                } else {
-                    if should_trigger_keyboard_navigation(e) {
-                        is_navigating_with_keyboard.write().unwrap_throw().set(true);
-                    }
-                }
-            }
-        };
         let options = EventListenerOptions::run_in_capture_phase();
-        let listener = EventListener::new_with_options(&win, "keydown", options, on_key_down);
+        let listener = EventListener::new_with_options(&doc, "focusin", options, move |event| {
+            if let Some(iframe) = event
+                .target()
+                .and_then(|target| target.dyn_into::<HtmlIFrameElement>().ok())
+            {
+                instrument(&iframe);
+            }
+        });
         listener_list.push(SendWrapper::new(listener));
+    }
 
-        let options = EventListenerOptions::run_in_capture_phase();
-        let listener =
-            EventListener::new_with_options(&win, "touchstart", options, on_mouse_or_touch.clone());
-        listener_list.push(SendWrapper::new(listener));
+    listener_list
+}
 
-        let options = EventListenerOptions::run_in_capture_phase();
-        let listener =
-            EventListener::new_with_options(&win, "touchend", options, on_mouse_or_touch.clone());
-        listener_list.push(SendWrapper::new(listener));
+/// Feeds the keyborg focus events reaching `doc` into the driver.
+/// When `is_attached`, the state of the JavaScript keyborg core is the only source, the
+/// keyborg focus in events only show the focus.
+fn add_focus_listeners(
+    doc: &Document,
+    driver: &ModalityDriver,
+    is_attached: bool,
+) -> Vec<SendWrapper<EventListener>> {
+    let mut listener_list = vec![];
+
+    let on_focus_in = {
+        let driver = driver.clone();
+        move |e: KeyborgFocusInEvent| {
+            // Without the key and pointer listeners, a focus moved by a click would be taken
+            // for a screen reader.
+            if !is_attached {
+                driver.dispatch(ModalityEvent::FocusIn {
+                    has_related_target: e.related_target.is_some(),
+                    is_focused_programmatically: e.is_focused_programmatically.as_bool(),
+                    focus_visible: e.focus_visible,
+                });
+            }
 
-        let options = EventListenerOptions::run_in_capture_phase();
-        let listener =
-            EventListener::new_with_options(&win, "touchcancel", options, on_mouse_or_touch);
+            if let (Some(focus_visible), Some(target)) = (&driver.focus_visible, e.target) {
+                let machine = driver.machine.read().unwrap_throw();
+                if machine.is_navigating_with_keyboard() {
+                    focus_visible.show(target);
+                }
+            }
+        }
+    };
+    let listener = on_keyborg_focus_in(doc, on_focus_in);
+    listener_list.push(SendWrapper::new(listener));
+
+    if let Some(focus_visible) = driver.focus_visible.clone() {
+        let listener = on_keyborg_focus_out(doc, move |e| {
+            if let Some(target) = e.target {
+                focus_visible.hide_on(&target);
+            }
+        });
         listener_list.push(SendWrapper::new(listener));
+    }
 
-        setup_focus_event(&win);
+    listener_list
+}
 
-        Self {
-            id: format!("c{id}"),
-            win: SendWrapper::new(win),
-            is_mouse_or_touch_used_timer,
-            dismiss_timer,
-            trigger_keys,
-            dismiss_keys,
-            is_navigating_with_keyboard,
-            _listener_list: listener_list,
-        }
+fn pointer_input(e: &web_sys::MouseEvent, pointer_type: PointerType) -> PointerInput {
+    PointerInput {
+        pointer_type,
+        buttons: e.buttons(),
+        at_origin: e.client_x() == 0 && e.client_y() == 0 && e.screen_x() == 0 && e.screen_y() == 0,
     }
 }
 
 impl Drop for KeyborgCore {
     fn drop(&mut self) {
-        let Self {
-            win,
-            is_mouse_or_touch_used_timer,
-            dismiss_timer,
-            ..
-        } = self;
-
-        if let Some(timer) = is_mouse_or_touch_used_timer.read().unwrap_throw().clone() {
-            win.clear_timeout_with_handle(timer);
-        }
+        self.driver.clear_timers();
+        self.driver.reflect_navigating(false);
+        self.driver.reflect_focus_visible(false);
+        self.frames.clear();
 
-        if let Some(timer) = dismiss_timer.read().unwrap_throw().clone() {
-            win.clear_timeout_with_handle(timer);
+        if !self.is_attached {
+            dispose_focus_event(self.win.clone().take());
         }
-
-        dispose_focus_event(win.clone().take());
     }
 }
 
-type KeyborgCallback = Box<dyn Fn(bool) + Send + Sync>;
+fn next_id() -> usize {
+    let last_id = LAST_ID.get_or_init(Default::default);
+    let id = *last_id.read().unwrap_throw() + 1;
+    *last_id.write().unwrap_throw() = id;
+    id
+}
+
+/// Keeps the keyboard navigation state pinned, see [`Keyborg::lock_navigating_with_keyboard`].
+#[must_use = "the lock is released when dropped"]
+pub struct KeyborgLock {
+    id: usize,
+    core: Option<Weak<KeyborgCore>>,
+}
+
+impl KeyborgLock {
+    /// Releases the lock, same as dropping it.
+    pub fn release(self) {}
+}
+
+impl Drop for KeyborgLock {
+    fn drop(&mut self) {
+        if let Some(core) = self.core.take().and_then(|core| core.upgrade()) {
+            core.driver.dispatch(ModalityEvent::Unlock { id: self.id });
+        }
+    }
+}
 
 pub struct Keyborg {
     id: String,
-    core: Option<Arc<KeyborgCore>>,
-    cb: Vec<KeyborgCallback>,
+    win: SendWrapper<Window>,
+    // The core is owned by `KEYBORG_MAP`, `None` once disposed.
+    core: RwLock<Option<Weak<KeyborgCore>>>,
+    cb: Subscribers<bool>,
+    modality_cb: Subscribers<InputModality>,
 }
 
 impl Keyborg {
-    /// Updates all subscribed callbacks with the keyboard navigation state
-    fn update(&self, is_navigating_with_keyboard: bool) {
-        self.cb
-            .iter()
-            .for_each(|callback| callback(is_navigating_with_keyboard));
-    }
-
-    pub fn create(win: Window, props: Option<KeyborgProps>) -> Arc<RwLock<Self>> {
-        let keyborg = Arc::new(RwLock::new(Self::new()));
-        let id = { keyborg.read().unwrap_throw().id.clone() };
-
-        let init = {
-            let keyborg = keyborg.clone();
-            let id = id.clone();
-            move || {
-                let core = Arc::new(KeyborgCore::new(win, props));
-                {
-                    let mut keyborg = keyborg.write().unwrap_throw();
-                    keyborg.core = Some(core.clone());
-                }
+    pub fn create(win: Window, props: Option<KeyborgProps>) -> KeyborgHandle {
+        let keyborg = Arc::new(Self::new(win.clone()));
 
-                let mut keyborg_map = KeyborgMap::new(core);
-                keyborg_map.refs.insert(id, keyborg.clone());
+        let current = KEYBORG_MAP.read().unwrap_throw().core(&win).cloned();
+        // The core is created without holding the lock, setting it up may dispatch events.
+        let core = current.unwrap_or_else(|| Arc::new(KeyborgCore::new(win.clone(), props)));
 
-                RwLock::new(Some(keyborg_map))
-            }
-        };
-
-        let current = KEYBORG_MAP.get_or_init(init.clone());
-        if let Some(current) = current.write().unwrap_throw().as_mut() {
-            {
-                let mut keyborg = keyborg.write().unwrap_throw();
-                keyborg.core = Some(current.core.clone());
-            }
-            current.refs.insert(id, keyborg.clone());
-        } else {
-            let _ = KEYBORG_MAP.set(init());
-        }
+        let core = KEYBORG_MAP.write().unwrap_throw().insert(
+            SendWrapper::new(win),
+            keyborg.id.clone(),
+            Arc::downgrade(&keyborg),
+            core,
+        );
+        *keyborg.core.write().unwrap_throw() = Some(Arc::downgrade(&core));
 
-        keyborg
+        KeyborgHandle(keyborg)
     }
 
-    fn new() -> Self {
-        let last_id = LAST_ID.get_or_init(Default::default);
-        let id = *last_id.read().unwrap_throw() + 1;
-        *last_id.write().unwrap_throw() = id;
-
+    fn new(win: Window) -> Self {
         Self {
-            id: format!("k{id}"),
-            core: None,
-            cb: vec![],
+            id: format!("k{}", next_id()),
+            win: SendWrapper::new(win),
+            core: Default::default(),
+            cb: Default::default(),
+            modality_cb: Default::default(),
         }
     }
 
+    fn core(&self) -> Option<Arc<KeyborgCore>> {
+        self.core.read().unwrap_throw().as_ref()?.upgrade()
+    }
+
+    /// Releases the instance, the core of the window is disposed with its last instance.
+    /// Disposing again does nothing, the handles of the instance are released when dropped.
     pub fn dispose(&self) {
-        let Self { id, .. } = self;
-        let current = KEYBORG_MAP.get().unwrap_throw();
+        if self.core.write().unwrap_throw().take().is_none() {
+            // Already disposed.
+            return;
+        }
+        // Cancels the pending `wait_until` futures and ends the `changes` streams, also the
+        // ones requested after.
+        self.cb.close();
+        self.modality_cb.close();
+
+        let released = KEYBORG_MAP
+            .write()
+            .unwrap_throw()
+            .release(&self.win, &self.id);
+        // The core is dropped after releasing the lock.
+        match released {
+            Released::Last(core) => drop(core),
+            Released::Shared => {}
+            Released::Unknown => {
+                if cfg!(debug_assertions) {
+                    web_sys::console::error_1(&JsValue::from(&format!(
+                        "Keyborg instance {} is being disposed incorrectly.",
+                        self.id
+                    )));
+                }
+            }
+        }
+    }
 
-        let (is_remove, is_empty) = if let Some(current) = current.write().unwrap_throw().as_mut() {
-            current.refs.remove(id);
+    /// @returns Whether the user is navigating with keyboard
+    pub fn is_navigating_with_keyboard(&self) -> bool {
+        self.core()
+            .is_some_and(|core| core.is_navigating_with_keyboard())
+    }
 
-            (current.refs.remove(id).is_some(), current.refs.is_empty())
-        } else {
-            Default::default()
-        };
+    /// @returns Whether the calls to `focus()` are detected, otherwise the keyborg focus in
+    /// events report [`ProgrammaticFocus::Unknown`](crate::ProgrammaticFocus::Unknown) and
+    /// focus moved by a screen reader is not told apart
+    pub fn is_programmatic_focus_detected(&self) -> bool {
+        self.core()
+            .is_some_and(|core| is_programmatic_focus_detected(&core.win))
+    }
 
-        if is_remove && is_empty {
-            // drop(core);
-            *current.write().unwrap_throw() = None;
+    /// @returns How the user is currently interacting with the page
+    pub fn current_modality(&self) -> InputModality {
+        self.core()
+            .map_or_else(Default::default, |core| core.modality())
+    }
+
+    /// Sets the keyboard navigation state, for example when a command palette was opened
+    /// with a shortcut. Has no effect while the state is locked.
+    pub fn set_navigating_with_keyboard(&self, is_navigating_with_keyboard: bool) {
+        if let Some(core) = self.core() {
+            core.driver
+                .dispatch(ModalityEvent::Set(is_navigating_with_keyboard));
         }
+    }
 
-        if !is_remove && cfg!(debug_assertions) {
-            web_sys::console::error_1(&JsValue::from(&format!(
-                "Keyborg instance {id} is being disposed incorrectly."
-            )));
+    /// Pins the keyboard navigation state until the returned lock is released or dropped,
+    /// neither mouse nor keys change it meanwhile. When several locks are held, the latest
+    /// one is in effect.
+    pub fn lock_navigating_with_keyboard(&self, is_navigating_with_keyboard: bool) -> KeyborgLock {
+        let id = next_id();
+        if let Some(core) = self.core() {
+            core.driver.dispatch(ModalityEvent::Lock {
+                id,
+                is_navigating_with_keyboard,
+            });
+        }
+
+        KeyborgLock {
+            id,
+            core: self.core.read().unwrap_throw().clone(),
         }
     }
 
-    /// @returns Whether the user is navigating with keyboard
-    pub fn is_navigating_with_keyboard(&self) -> bool {
-        if let Some(core) = &self.core {
-            core.is_navigating_with_keyboard.read().unwrap_throw().get()
-        } else {
-            false
+    /// @returns Whether the keyboard navigation state is locked
+    pub fn is_locked(&self) -> bool {
+        self.core()
+            .is_some_and(|core| core.driver.machine.read().unwrap_throw().is_locked())
+    }
+
+    /// @returns The timing windows currently used by the shared core
+    pub fn timings(&self) -> KeyborgTimings {
+        self.core().map_or_else(Default::default, |core| {
+            core.driver.machine.read().unwrap_throw().timings()
+        })
+    }
+
+    /// Changes the timing windows of the shared core, affecting all keyborg instances.
+    /// Already started timers keep their previous duration.
+    pub fn set_timings(&self, timings: KeyborgTimings) {
+        if let Some(core) = self.core() {
+            core.driver
+                .machine
+                .write()
+                .unwrap_throw()
+                .set_timings(timings);
         }
     }
 
     /// callback - Called when the keyboard navigation state changes
-    pub fn subscribe(&mut self, callback: impl Fn(bool) + Send + Sync + 'static) {
-        self.cb.push(Box::new(callback));
+    ///
+    /// @returns The subscription removing the callback when dropped, inactive when this
+    /// instance is disposed
+    pub fn subscribe(&self, callback: impl Fn(bool) + Send + Sync + 'static) -> Subscription {
+        self.cb.subscribe(callback, false)
+    }
+
+    /// callback - Called once, on the next keyboard navigation state change
+    pub fn subscribe_once(&self, callback: impl Fn(bool) + Send + Sync + 'static) -> Subscription {
+        self.cb.subscribe(callback, true)
     }
 
-    /// @param callback - Registered with subscribe
-    pub fn unsubscribe(callback: KeyborgCallback) {
-        // const index = this._cb.indexOf(callback);
+    /// callback - Called when the input modality changes
+    pub fn subscribe_modality(
+        &self,
+        callback: impl Fn(InputModality) + Send + Sync + 'static,
+    ) -> Subscription {
+        self.modality_cb.subscribe(callback, false)
+    }
+
+    /// @returns A stream of the keyboard navigation state changes, unsubscribed when dropped
+    /// and ended when this instance is disposed
+    pub fn changes(&self) -> KeyborgChanges {
+        KeyborgChanges::new(&self.cb)
+    }
+
+    /// @returns A future resolving once the keyboard navigation state is `state`,
+    /// immediately when it already is, or to `Err(Canceled)` when this instance is disposed
+    /// or dropped first
+    pub fn wait_until(&self, state: bool) -> WaitUntil {
+        WaitUntil::new(&self.cb, self.is_navigating_with_keyboard(), state)
+    }
+
+    /// @param subscription - Returned by subscribe
+    pub fn unsubscribe(&self, subscription: Subscription) {
+        subscription.cancel();
+    }
+}
+
+impl Drop for Keyborg {
+    fn drop(&mut self) {
+        self.dispose();
+    }
+}
+
+/// A keyborg instance created with [`Keyborg::create`]. The clones share the instance, which
+/// is disposed when the last one is dropped, or earlier with [`Keyborg::dispose`].
+#[derive(Clone)]
+pub struct KeyborgHandle(Arc<Keyborg>);
+
+impl KeyborgHandle {
+    pub(crate) fn downgrade(&self) -> Weak<Keyborg> {
+        Arc::downgrade(&self.0)
+    }
+}
+
+impl Deref for KeyborgHandle {
+    type Target = Keyborg;
+
+    fn deref(&self) -> &Keyborg {
+        &self.0
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use crate::focus_event::KEYBORG_FOCUSIN;
+    use js_sys::Object;
+    use wasm_bindgen_test::wasm_bindgen_test;
+    use web_sys::{CustomEvent, CustomEventInit, EventTarget};
+
+    fn focus_in(target: &EventTarget, is_focused_programmatically: bool) {
+        let detail = Object::new();
+        Reflect::set(&detail, &"relatedTarget".into(), &Object::new()).unwrap();
+        Reflect::set(
+            &detail,
+            &"isFocusedProgrammatically".into(),
+            &is_focused_programmatically.into(),
+        )
+        .unwrap();
+        let init = CustomEventInit::new();
+        init.set_detail(&detail);
+        let event = CustomEvent::new_with_event_init_dict(KEYBORG_FOCUSIN, &init).unwrap();
+        target.dispatch_event(&event).unwrap();
+    }
+
+    #[wasm_bindgen_test]
+    fn attached_focus_in_keeps_the_js_state() {
+        let js_core = Object::new();
+        let driver = ModalityDriver {
+            win: SendWrapper::new(Object::new().unchecked_into()),
+            machine: Default::default(),
+            is_mouse_or_touch_used_timer: Default::default(),
+            dismiss_timer: Default::default(),
+            tab_timer: Default::default(),
+            navigating_indicators: Arc::new([]),
+            focus_visible: None,
+            js_sync: Some(JsSync::Attached(SendWrapper::new(js_core.clone().into()))),
+        };
+        let target = EventTarget::new().unwrap();
+        let _listener_list = add_focus_listeners(target.unchecked_ref(), &driver, true);
+
+        // The JavaScript keyborg core left the keyboard navigation mode on mousedown.
+        driver.dispatch(ModalityEvent::Set(true));
+        driver.dispatch(ModalityEvent::Set(false));
+        // The click moved the focus.
+        focus_in(&target, false);
 
-        // if (index >= 0) {
-        //     this._cb.splice(index, 1);
-        // }
+        assert!(!driver.machine.read().unwrap().is_navigating_with_keyboard());
+        assert!(!is_js_navigating(&js_core));
     }
 }
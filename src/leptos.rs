@@ -0,0 +1,75 @@
+use crate::{binding::KeyborgBinding, InputModality, KeyborgProps};
+use ::leptos::prelude::*;
+
+#[derive(Clone, Copy)]
+struct KeyborgContext {
+    is_navigating_with_keyboard: RwSignal<bool>,
+    modality: RwSignal<InputModality>,
+}
+
+impl KeyborgContext {
+    /// The keyborg instance is created in an effect, so nothing runs during server rendering,
+    /// and disposed when the current owner is cleaned up.
+    fn new(props: Option<KeyborgProps>) -> Self {
+        let is_navigating_with_keyboard = RwSignal::new(false);
+        let modality = RwSignal::new(InputModality::default());
+
+        Effect::new(move |_| {
+            let binding = KeyborgBinding::new(
+                window(),
+                props.clone(),
+                move |value| {
+                    is_navigating_with_keyboard.try_set(value);
+                },
+                move |value| {
+                    modality.try_set(value);
+                },
+            );
+            is_navigating_with_keyboard.set(binding.is_navigating_with_keyboard());
+            modality.set(binding.current_modality());
+
+            on_cleanup(move || drop(binding));
+        });
+
+        Self {
+            is_navigating_with_keyboard,
+            modality,
+        }
+    }
+}
+
+/// Provides a keyborg instance to the descendants of the current owner, shared by
+/// `use_keyborg` and `use_keyborg_modality`.
+pub fn provide_keyborg(props: Option<KeyborgProps>) {
+    provide_context(KeyborgContext::new(props));
+}
+
+/// Provides a keyborg instance to its children.
+#[component]
+pub fn KeyborgProvider(
+    #[prop(optional, into)] props: Option<KeyborgProps>,
+    children: Children,
+) -> impl IntoView {
+    provide_keyborg(props);
+
+    children()
+}
+
+fn use_keyborg_context() -> KeyborgContext {
+    use_context::<KeyborgContext>().unwrap_or_else(|| {
+        let context = KeyborgContext::new(None);
+        provide_context(context);
+        context
+    })
+}
+
+/// @returns Whether the user is navigating with keyboard, using the provided keyborg
+/// instance or creating one scoped to the current owner.
+pub fn use_keyborg() -> Signal<bool> {
+    use_keyborg_context().is_navigating_with_keyboard.into()
+}
+
+/// @returns How the user is currently interacting with the page
+pub fn use_keyborg_modality() -> Signal<InputModality> {
+    use_keyborg_context().modality.into()
+}
@@ -1,6 +1,40 @@
+#[cfg(any(feature = "leptos", feature = "yew", feature = "dioxus"))]
+mod binding;
+mod compat;
+#[cfg(feature = "dioxus")]
+pub mod dioxus;
+mod editable;
+mod events;
 mod focus_event;
+mod frame_sync;
+mod frames;
+mod ignore;
+mod indicator;
 mod js;
+#[cfg(feature = "js-api")]
+mod js_api;
+mod key;
 mod keyborg;
+#[cfg(feature = "leptos")]
+pub mod leptos;
+mod modality;
+mod props;
+mod registry;
+mod stream;
+mod subscription;
+#[cfg(feature = "yew")]
+pub mod yew;
 
-pub use focus_event::KEYBORG_FOCUSIN;
-pub use keyborg::Keyborg;
+pub use events::{
+    on_keyborg_focus_in, on_keyborg_focus_out, KeyborgFocusInEvent, KeyborgFocusOutEvent,
+    ProgrammaticFocus,
+};
+pub use focus_event::{KEYBORG_FOCUSIN, KEYBORG_FOCUSOUT};
+pub use frame_sync::{FocusDirection, FrameSyncOptions, KeyborgFrameSync, FRAME_SYNC_VERSION};
+pub use ignore::KEYBORG_IGNORE;
+pub use key::{Key, KeyMatcher, Modifiers, ParseKeyMatcherError};
+pub use keyborg::{Keyborg, KeyborgHandle, KeyborgLock};
+pub use modality::{InputModality, KeyborgTimings, PointerType, TabPolicy};
+pub use props::KeyborgProps;
+pub use stream::{Canceled, KeyborgChanges, WaitUntil};
+pub use subscription::Subscription;
@@ -0,0 +1,873 @@
+use crate::{
+    key::{KeyRule, Modifiers},
+    props::KeyborgProps,
+};
+
+/// Timing windows of the keyboard navigation mode, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyborgTimings {
+    /// When a key from dismiss_keys is pressed and the focus is not moved
+    /// during this time, dismiss the keyboard navigation mode.
+    pub dismiss_timeout: i32,
+    /// How long focus changes after a mouse event are not attributed to a screen reader.
+    pub mouse_suppression: i32,
+    /// How long focus changes after a touch event are not attributed to a screen reader.
+    pub touch_suppression: i32,
+    /// How long focus changes after a pen event are not attributed to a screen reader.
+    pub pen_suppression: i32,
+}
+
+impl Default for KeyborgTimings {
+    fn default() -> Self {
+        Self {
+            dismiss_timeout: 500,
+            mouse_suppression: 1000,
+            touch_suppression: 1000,
+            pen_suppression: 1000,
+        }
+    }
+}
+
+impl KeyborgTimings {
+    pub fn pointer_suppression(&self, pointer_type: PointerType) -> i32 {
+        match pointer_type {
+            PointerType::Mouse => self.mouse_suppression,
+            PointerType::Touch => self.touch_suppression,
+            PointerType::Pen => self.pen_suppression,
+        }
+    }
+}
+
+/// How the user is interacting with the page.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum InputModality {
+    /// No interaction has been observed yet, or the keyboard navigation mode was dismissed
+    /// with a dismiss key.
+    #[default]
+    Unknown,
+    Mouse,
+    Touch,
+    Pen,
+    Keyboard,
+    /// The focus moved without any key or pointer events, most likely by a screen reader.
+    ScreenReader,
+    /// The focus was moved by a script outside of the keyboard navigation mode.
+    Programmatic,
+}
+
+impl InputModality {
+    /// Whether this modality counts as navigating with keyboard.
+    pub fn is_keyboard_navigation(&self) -> bool {
+        matches!(self, Self::Keyboard | Self::ScreenReader)
+    }
+}
+
+/// `PointerEvent.pointerType`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PointerType {
+    Mouse,
+    Touch,
+    Pen,
+}
+
+impl PointerType {
+    /// Parses `PointerEvent.pointerType`, unknown and empty values are treated as mouse.
+    pub fn from_pointer_type(pointer_type: &str) -> Self {
+        match pointer_type {
+            "touch" => Self::Touch,
+            "pen" => Self::Pen,
+            _ => Self::Mouse,
+        }
+    }
+
+    fn modality(self) -> InputModality {
+        match self {
+            Self::Mouse => InputModality::Mouse,
+            Self::Touch => InputModality::Touch,
+            Self::Pen => InputModality::Pen,
+        }
+    }
+}
+
+/// A mouse, touch or pen event as seen by the [`ModalityMachine`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PointerInput {
+    pub pointer_type: PointerType,
+    /// `MouseEvent.buttons`
+    pub buttons: u16,
+    /// Whether both client and screen coordinates are zero.
+    pub at_origin: bool,
+}
+
+impl PointerInput {
+    pub fn touch() -> Self {
+        Self {
+            pointer_type: PointerType::Touch,
+            buttons: 0,
+            at_origin: false,
+        }
+    }
+}
+
+/// How Tab pressed in an editable element is treated, as rich text editors may use it
+/// for indentation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum TabPolicy {
+    /// Tab always triggers the keyboard navigation mode.
+    Always,
+    /// In editable elements, Tab triggers the keyboard navigation mode only when the app
+    /// did not call `preventDefault()` on it and the focus moved after it.
+    #[default]
+    VerifyInEditable,
+}
+
+/// A keydown as seen by the [`ModalityMachine`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyInput {
+    /// `KeyboardEvent.key`
+    pub key: String,
+    /// `KeyboardEvent.code`
+    pub code: String,
+    /// `KeyboardEvent.keyCode`
+    pub key_code: u32,
+    /// Modifiers held while pressing the key.
+    pub modifiers: Modifiers,
+    /// Whether the currently focused element accepts text input.
+    pub is_editable: bool,
+}
+
+/// Platform independent input fed to the [`ModalityMachine`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModalityEvent {
+    KeyDown(KeyInput),
+    /// `mousedown`, `pointerdown` or any of the touch events.
+    Pointer(PointerInput),
+    /// A pointer event inside an ignored subtree. It does not change the mode, but the focus
+    /// it moves is not attributed to a screen reader either.
+    IgnoredPointer(PointerType),
+    /// `keyborg:focusin`
+    FocusIn {
+        has_related_target: bool,
+        /// `None` when the programmatic focus detection is not working.
+        is_focused_programmatically: Option<bool>,
+        /// The `focusVisible` option passed to `focus()`, when it was explicitly set.
+        focus_visible: Option<bool>,
+    },
+    /// The timer started with [`ModalityEffect::StartPointerTimer`] has fired.
+    PointerTimerFired,
+    /// The timer started with [`ModalityEffect::StartDismissTimer`] has fired.
+    DismissTimerFired {
+        /// Whether the focused element changed since the timer was started.
+        focus_moved: bool,
+    },
+    /// The Tab requested to be verified with [`ModalityEffect::VerifyTab`] was handled.
+    TabVerified {
+        /// Whether the default action was not prevented and the focus moved.
+        navigated: bool,
+    },
+    /// The app sets the keyboard navigation state.
+    Set(bool),
+    /// The app pins the keyboard navigation state until the lock with the same `id` is
+    /// released, the latest lock wins.
+    Lock {
+        id: usize,
+        is_navigating_with_keyboard: bool,
+    },
+    Unlock {
+        id: usize,
+    },
+}
+
+/// What the platform adapter has to do after an event was handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModalityEffect {
+    /// The input modality changed to the given value.
+    ModalityChanged(InputModality),
+    /// The keyboard navigation state changed to the given value.
+    Changed(bool),
+    /// (Re)starts the pointer timer, replacing the pending one.
+    StartPointerTimer(i32),
+    /// (Re)starts the dismiss timer, replacing the pending one.
+    StartDismissTimer(i32),
+    /// Reports with [`ModalityEvent::TabVerified`] whether the current Tab keydown moved the
+    /// focus, once the app has handled it.
+    VerifyTab,
+}
+
+/// Decides when to enter and leave the keyboard navigation mode.
+#[derive(Debug, Default)]
+pub struct ModalityMachine {
+    modality: InputModality,
+    // The pointer used recently, if any.
+    pointer_used: Option<PointerType>,
+    trigger_keys: KeyRule,
+    dismiss_keys: KeyRule,
+    timings: KeyborgTimings,
+    tab_policy: TabPolicy,
+    // A Tab in an editable element is waiting to be verified.
+    is_tab_pending: bool,
+    // Held locks with their pinned state, the last one is in effect.
+    locks: Vec<(usize, bool)>,
+}
+
+impl ModalityMachine {
+    pub fn new(props: &KeyborgProps) -> Self {
+        Self {
+            trigger_keys: props.trigger_keys.clone(),
+            dismiss_keys: props.dismiss_keys.clone(),
+            timings: props.timings,
+            tab_policy: props.tab_policy,
+            ..Default::default()
+        }
+    }
+
+    pub fn timings(&self) -> KeyborgTimings {
+        self.timings
+    }
+
+    /// Takes effect from the next started timer on.
+    pub fn set_timings(&mut self, timings: KeyborgTimings) {
+        self.timings = timings;
+    }
+
+    pub fn modality(&self) -> InputModality {
+        self.modality
+    }
+
+    pub fn is_navigating_with_keyboard(&self) -> bool {
+        self.modality.is_keyboard_navigation()
+    }
+
+    pub fn is_locked(&self) -> bool {
+        !self.locks.is_empty()
+    }
+
+    pub fn handle(&mut self, event: ModalityEvent) -> Vec<ModalityEffect> {
+        let mut effects = vec![];
+
+        if self.is_locked() {
+            match event {
+                ModalityEvent::PointerTimerFired => self.pointer_used = None,
+                ModalityEvent::TabVerified { .. } => self.is_tab_pending = false,
+                ModalityEvent::Lock { .. } | ModalityEvent::Unlock { .. } => {
+                    self.handle_lock(event, &mut effects)
+                }
+                // Neither the user nor the app changes the locked state.
+                _ => {}
+            }
+
+            return effects;
+        }
+
+        match event {
+            ModalityEvent::KeyDown(key) => {
+                if self.is_navigating_with_keyboard() {
+                    // Keys pressed in the keyboard navigation mode tell the screen reader
+                    // apart from the keyboard.
+                    self.set(InputModality::Keyboard, &mut effects);
+
+                    if self.should_dismiss_keyboard_navigation(&key) {
+                        effects.push(ModalityEffect::StartDismissTimer(
+                            self.timings.dismiss_timeout,
+                        ));
+                    }
+                } else if self.should_trigger_keyboard_navigation(&key) {
+                    if key.key == "Tab"
+                        && key.is_editable
+                        && self.tab_policy == TabPolicy::VerifyInEditable
+                    {
+                        self.is_tab_pending = true;
+                        effects.push(ModalityEffect::VerifyTab);
+                    } else {
+                        self.set(InputModality::Keyboard, &mut effects);
+                    }
+                }
+            }
+            ModalityEvent::Pointer(pointer) => {
+                if pointer.pointer_type == PointerType::Mouse {
+                    if pointer.buttons == 0 || pointer.at_origin {
+                        // This is most likely an event triggered by the screen reader to perform
+                        // an action on an element, do not dismiss the keyboard navigation mode.
+                        return effects;
+                    }
+
+                    if matches!(
+                        self.pointer_used,
+                        Some(PointerType::Touch | PointerType::Pen)
+                    ) {
+                        // A compatibility mouse event fired by the browser after touch or pen.
+                        return effects;
+                    }
+                }
+
+                self.on_pointer(pointer.pointer_type, &mut effects);
+            }
+            ModalityEvent::IgnoredPointer(pointer_type) => {
+                self.suppress_focus_in(pointer_type, &mut effects);
+            }
+            ModalityEvent::FocusIn {
+                has_related_target,
+                is_focused_programmatically,
+                focus_visible,
+            } => {
+                // The app told whether the focus should be shown, which outweighs the guesses.
+                match focus_visible {
+                    Some(true) => {
+                        self.force(true, &mut effects);
+                        return effects;
+                    }
+                    Some(false) => {
+                        if self.is_navigating_with_keyboard() {
+                            self.set(InputModality::Programmatic, &mut effects);
+                        }
+                        return effects;
+                    }
+                    None => {}
+                }
+
+                // When the focus is moved not programmatically and without keydown events,
+                // it is likely that the focus is moved by screen reader (as it might swallow
+                // the events when the screen reader shortcuts are used). The screen reader
+                // usage is keyboard navigation.
+
+                if self.pointer_used.is_some() {
+                    // There was a mouse or touch event recently.
+                    return effects;
+                }
+
+                if self.is_tab_pending {
+                    // The focus moved by Tab, it is up to the verification.
+                    return effects;
+                }
+
+                if self.is_navigating_with_keyboard() || !has_related_target {
+                    return effects;
+                }
+
+                match is_focused_programmatically {
+                    Some(false) => self.set(InputModality::ScreenReader, &mut effects),
+                    Some(true) => self.set(InputModality::Programmatic, &mut effects),
+                    // The programmatic focus detection is not working.
+                    None => {}
+                }
+            }
+            ModalityEvent::PointerTimerFired => self.pointer_used = None,
+            ModalityEvent::DismissTimerFired { focus_moved } => {
+                if !focus_moved {
+                    // Esc was pressed, currently focused element hasn't changed.
+                    // Just dismiss the keyboard navigation mode.
+                    self.set(InputModality::Unknown, &mut effects);
+                }
+            }
+            ModalityEvent::TabVerified { navigated } => {
+                if std::mem::take(&mut self.is_tab_pending) && navigated {
+                    self.set(InputModality::Keyboard, &mut effects);
+                }
+            }
+            ModalityEvent::Set(val) => self.force(val, &mut effects),
+            ModalityEvent::Lock { .. } | ModalityEvent::Unlock { .. } => {
+                self.handle_lock(event, &mut effects)
+            }
+        }
+
+        effects
+    }
+
+    fn handle_lock(&mut self, event: ModalityEvent, effects: &mut Vec<ModalityEffect>) {
+        match event {
+            ModalityEvent::Lock {
+                id,
+                is_navigating_with_keyboard,
+            } => {
+                self.locks.push((id, is_navigating_with_keyboard));
+                self.force(is_navigating_with_keyboard, effects);
+            }
+            ModalityEvent::Unlock { id } => {
+                self.locks.retain(|(lock_id, _)| *lock_id != id);
+                if let Some((_, val)) = self.locks.last() {
+                    self.force(*val, effects);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Keeps the modality when it already matches the keyboard navigation state.
+    fn force(&mut self, val: bool, effects: &mut Vec<ModalityEffect>) {
+        if self.is_navigating_with_keyboard() != val {
+            let modality = if val {
+                InputModality::Keyboard
+            } else {
+                InputModality::Unknown
+            };
+            self.set(modality, effects);
+        }
+    }
+
+    fn set(&mut self, modality: InputModality, effects: &mut Vec<ModalityEffect>) {
+        if self.modality == modality {
+            return;
+        }
+
+        let was_navigating_with_keyboard = self.is_navigating_with_keyboard();
+        self.modality = modality;
+        effects.push(ModalityEffect::ModalityChanged(modality));
+
+        if was_navigating_with_keyboard != self.is_navigating_with_keyboard() {
+            effects.push(ModalityEffect::Changed(!was_navigating_with_keyboard));
+        }
+    }
+
+    fn on_pointer(&mut self, pointer_type: PointerType, effects: &mut Vec<ModalityEffect>) {
+        self.suppress_focus_in(pointer_type, effects);
+        self.set(pointer_type.modality(), effects);
+    }
+
+    /// Keeps the focus moved by the pointer from being attributed to a screen reader.
+    fn suppress_focus_in(&mut self, pointer_type: PointerType, effects: &mut Vec<ModalityEffect>) {
+        self.pointer_used = Some(pointer_type);
+        effects.push(ModalityEffect::StartPointerTimer(
+            self.timings.pointer_suppression(pointer_type),
+        ));
+    }
+
+    fn should_dismiss_keyboard_navigation(&self, key: &KeyInput) -> bool {
+        self.dismiss_keys.matches(key, false)
+    }
+
+    /// @returns whether the keyboard event should trigger keyboard navigation mode
+    fn should_trigger_keyboard_navigation(&self, key: &KeyInput) -> bool {
+        // Tab in editable elements is verified according to the TabPolicy.
+        if key.key == "Tab" {
+            return !self.trigger_keys.is_excluded(key);
+        }
+
+        self.trigger_keys.matches(key, true) && !key.is_editable
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key::KeyMatcher;
+
+    const POINTER_TIMEOUT: i32 = 1000;
+    const DISMISS_TIMEOUT: i32 = 500;
+
+    fn key(key: &str, key_code: u32) -> ModalityEvent {
+        ModalityEvent::KeyDown(KeyInput {
+            key: key.to_string(),
+            code: String::new(),
+            key_code,
+            ..Default::default()
+        })
+    }
+
+    fn focus_in(is_focused_programmatically: Option<bool>) -> ModalityEvent {
+        ModalityEvent::FocusIn {
+            has_related_target: true,
+            is_focused_programmatically,
+            focus_visible: None,
+        }
+    }
+
+    fn changed(modality: InputModality) -> Vec<ModalityEffect> {
+        let mut effects = vec![ModalityEffect::ModalityChanged(modality)];
+        if modality.is_keyboard_navigation() {
+            effects.push(ModalityEffect::Changed(true));
+        }
+        effects
+    }
+
+    fn pointer(pointer_type: PointerType) -> ModalityEvent {
+        ModalityEvent::Pointer(PointerInput {
+            pointer_type,
+            buttons: 1,
+            at_origin: false,
+        })
+    }
+
+    const MOUSE_DOWN: ModalityEvent = ModalityEvent::Pointer(PointerInput {
+        pointer_type: PointerType::Mouse,
+        buttons: 1,
+        at_origin: false,
+    });
+
+    #[test]
+    fn any_key_triggers_by_default() {
+        let mut machine = ModalityMachine::default();
+
+        assert_eq!(
+            machine.handle(key("a", 65)),
+            changed(InputModality::Keyboard)
+        );
+        assert!(machine.is_navigating_with_keyboard());
+        assert_eq!(machine.handle(key("b", 66)), vec![]);
+    }
+
+    #[test]
+    fn trigger_keys_limit_triggering_except_tab() {
+        let mut machine = ModalityMachine::new(&KeyborgProps::new().trigger_keys(["ArrowDown"]));
+
+        assert_eq!(machine.handle(key("a", 65)), vec![]);
+        assert_eq!(
+            machine.handle(key("Tab", 9)),
+            changed(InputModality::Keyboard)
+        );
+
+        let mut machine = ModalityMachine::new(&KeyborgProps::new().trigger_keys(["ArrowDown"]));
+        assert_eq!(
+            machine.handle(key("ArrowDown", 40)),
+            changed(InputModality::Keyboard)
+        );
+    }
+
+    #[test]
+    fn excluded_chords_do_not_trigger() {
+        let props = KeyborgProps::new()
+            .trigger_exclude("Meta+*".parse::<KeyMatcher>().unwrap())
+            .trigger_exclude("Ctrl+Tab".parse::<KeyMatcher>().unwrap());
+        let mut machine = ModalityMachine::new(&props);
+        let chord = |key: &str, modifiers| {
+            ModalityEvent::KeyDown(KeyInput {
+                key: key.to_string(),
+                modifiers,
+                ..Default::default()
+            })
+        };
+
+        assert_eq!(machine.handle(chord("z", Modifiers::META)), vec![]);
+        assert_eq!(machine.handle(chord("Tab", Modifiers::CTRL)), vec![]);
+        assert_eq!(
+            machine.handle(chord("Tab", Modifiers::SHIFT)),
+            changed(InputModality::Keyboard)
+        );
+    }
+
+    #[test]
+    fn editable_elements_only_trigger_on_tab() {
+        let mut machine = ModalityMachine::new(&KeyborgProps::new().tab_policy(TabPolicy::Always));
+        let mut input = KeyInput {
+            key: "a".to_string(),
+            code: "KeyA".to_string(),
+            key_code: 65,
+            is_editable: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            machine.handle(ModalityEvent::KeyDown(input.clone())),
+            vec![]
+        );
+
+        input.key = "Tab".to_string();
+        input.key_code = 9;
+        assert_eq!(
+            machine.handle(ModalityEvent::KeyDown(input)),
+            changed(InputModality::Keyboard)
+        );
+    }
+
+    #[test]
+    fn tab_in_editable_elements_is_verified() {
+        let mut machine = ModalityMachine::default();
+        let tab = ModalityEvent::KeyDown(KeyInput {
+            key: "Tab".to_string(),
+            is_editable: true,
+            ..Default::default()
+        });
+
+        assert_eq!(machine.handle(tab.clone()), vec![ModalityEffect::VerifyTab]);
+        // The focus moved by Tab is not attributed to a screen reader.
+        assert_eq!(machine.handle(focus_in(Some(false))), vec![]);
+        assert_eq!(
+            machine.handle(ModalityEvent::TabVerified { navigated: false }),
+            vec![]
+        );
+        assert!(!machine.is_navigating_with_keyboard());
+
+        machine.handle(tab);
+        assert_eq!(
+            machine.handle(ModalityEvent::TabVerified { navigated: true }),
+            changed(InputModality::Keyboard)
+        );
+        assert_eq!(
+            machine.handle(ModalityEvent::TabVerified { navigated: true }),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn mouse_down_dismisses_and_suppresses_focus_in() {
+        let mut machine = ModalityMachine::default();
+        machine.handle(key("Tab", 9));
+
+        assert_eq!(
+            machine.handle(MOUSE_DOWN),
+            vec![
+                ModalityEffect::StartPointerTimer(POINTER_TIMEOUT),
+                ModalityEffect::ModalityChanged(InputModality::Mouse),
+                ModalityEffect::Changed(false)
+            ]
+        );
+        assert_eq!(machine.handle(focus_in(Some(false))), vec![]);
+
+        machine.handle(ModalityEvent::PointerTimerFired);
+        assert_eq!(
+            machine.handle(focus_in(Some(false))),
+            changed(InputModality::ScreenReader)
+        );
+    }
+
+    #[test]
+    fn ignored_pointers_keep_the_mode_and_suppress_focus_in() {
+        let mut machine = ModalityMachine::default();
+        assert_eq!(
+            machine.handle(ModalityEvent::IgnoredPointer(PointerType::Mouse)),
+            vec![ModalityEffect::StartPointerTimer(POINTER_TIMEOUT)]
+        );
+        // The click focused an element inside the ignored subtree.
+        assert_eq!(machine.handle(focus_in(Some(false))), vec![]);
+        assert!(!machine.is_navigating_with_keyboard());
+
+        machine.handle(key("Tab", 9));
+        machine.handle(ModalityEvent::IgnoredPointer(PointerType::Touch));
+        assert!(machine.is_navigating_with_keyboard());
+    }
+
+    #[test]
+    fn screen_reader_clicks_are_ignored() {
+        let mut machine = ModalityMachine::default();
+        machine.handle(key("Tab", 9));
+
+        let no_buttons = ModalityEvent::Pointer(PointerInput {
+            pointer_type: PointerType::Mouse,
+            buttons: 0,
+            at_origin: false,
+        });
+        let at_origin = ModalityEvent::Pointer(PointerInput {
+            pointer_type: PointerType::Mouse,
+            buttons: 1,
+            at_origin: true,
+        });
+        assert_eq!(machine.handle(no_buttons), vec![]);
+        assert_eq!(machine.handle(at_origin), vec![]);
+        assert!(machine.is_navigating_with_keyboard());
+    }
+
+    #[test]
+    fn touch_dismisses() {
+        let mut machine = ModalityMachine::default();
+        machine.handle(key("Tab", 9));
+
+        assert_eq!(
+            machine.handle(ModalityEvent::Pointer(PointerInput::touch())),
+            vec![
+                ModalityEffect::StartPointerTimer(POINTER_TIMEOUT),
+                ModalityEffect::ModalityChanged(InputModality::Touch),
+                ModalityEffect::Changed(false)
+            ]
+        );
+        assert_eq!(machine.modality(), InputModality::Touch);
+    }
+
+    #[test]
+    fn compatibility_mouse_events_after_touch_are_ignored() {
+        let mut machine = ModalityMachine::default();
+        machine.handle(ModalityEvent::Pointer(PointerInput::touch()));
+
+        assert_eq!(machine.handle(MOUSE_DOWN), vec![]);
+        assert_eq!(machine.modality(), InputModality::Touch);
+
+        machine.handle(ModalityEvent::PointerTimerFired);
+        assert_eq!(
+            machine.handle(MOUSE_DOWN),
+            vec![
+                ModalityEffect::StartPointerTimer(POINTER_TIMEOUT),
+                ModalityEffect::ModalityChanged(InputModality::Mouse)
+            ]
+        );
+    }
+
+    #[test]
+    fn pen_is_told_apart() {
+        let mut machine = ModalityMachine::default();
+
+        assert_eq!(
+            machine.handle(pointer(PointerType::Pen)),
+            vec![
+                ModalityEffect::StartPointerTimer(POINTER_TIMEOUT),
+                ModalityEffect::ModalityChanged(InputModality::Pen)
+            ]
+        );
+        assert_eq!(PointerType::from_pointer_type("pen"), PointerType::Pen);
+        assert_eq!(PointerType::from_pointer_type(""), PointerType::Mouse);
+    }
+
+    #[test]
+    fn focus_in_triggers_only_when_not_programmatic() {
+        let mut machine = ModalityMachine::default();
+
+        assert_eq!(machine.handle(focus_in(None)), vec![]);
+        assert_eq!(
+            machine.handle(focus_in(Some(true))),
+            changed(InputModality::Programmatic)
+        );
+        assert_eq!(
+            machine.handle(ModalityEvent::FocusIn {
+                has_related_target: false,
+                is_focused_programmatically: Some(false),
+                focus_visible: None,
+            }),
+            vec![]
+        );
+        assert_eq!(
+            machine.handle(focus_in(Some(false))),
+            changed(InputModality::ScreenReader)
+        );
+    }
+
+    #[test]
+    fn explicit_focus_visible_sets_the_state() {
+        let focus_visible = |focus_visible| ModalityEvent::FocusIn {
+            has_related_target: true,
+            is_focused_programmatically: Some(true),
+            focus_visible: Some(focus_visible),
+        };
+        let mut machine = ModalityMachine::default();
+        machine.handle(MOUSE_DOWN);
+
+        assert_eq!(
+            machine.handle(focus_visible(true)),
+            changed(InputModality::Keyboard)
+        );
+        assert_eq!(machine.handle(focus_visible(true)), vec![]);
+        assert_eq!(
+            machine.handle(focus_visible(false)),
+            vec![
+                ModalityEffect::ModalityChanged(InputModality::Programmatic),
+                ModalityEffect::Changed(false)
+            ]
+        );
+    }
+
+    #[test]
+    fn keys_in_screen_reader_mode_switch_to_keyboard() {
+        let mut machine = ModalityMachine::default();
+        machine.handle(focus_in(Some(false)));
+
+        assert_eq!(
+            machine.handle(key("ArrowDown", 40)),
+            vec![ModalityEffect::ModalityChanged(InputModality::Keyboard)]
+        );
+        assert!(machine.is_navigating_with_keyboard());
+    }
+
+    #[test]
+    fn dismiss_key_dismisses_when_focus_stays() {
+        let mut machine = ModalityMachine::new(&KeyborgProps::new().dismiss_keys([27]));
+        machine.handle(key("Tab", 9));
+
+        assert_eq!(machine.handle(key("a", 65)), vec![]);
+        assert_eq!(
+            machine.handle(key("Escape", 27)),
+            vec![ModalityEffect::StartDismissTimer(DISMISS_TIMEOUT)]
+        );
+        assert_eq!(
+            machine.handle(ModalityEvent::DismissTimerFired { focus_moved: true }),
+            vec![]
+        );
+
+        machine.handle(key("Escape", 27));
+        assert_eq!(
+            machine.handle(ModalityEvent::DismissTimerFired { focus_moved: false }),
+            vec![
+                ModalityEffect::ModalityChanged(InputModality::Unknown),
+                ModalityEffect::Changed(false)
+            ]
+        );
+    }
+
+    #[test]
+    fn timings_are_per_pointer_type_and_adjustable() {
+        let timings = KeyborgTimings {
+            touch_suppression: 2000,
+            ..Default::default()
+        };
+        let mut machine = ModalityMachine::new(&KeyborgProps::new().timings(timings));
+
+        assert_eq!(
+            machine.handle(ModalityEvent::Pointer(PointerInput::touch()))[0],
+            ModalityEffect::StartPointerTimer(2000)
+        );
+        assert_eq!(
+            machine.handle(pointer(PointerType::Pen))[0],
+            ModalityEffect::StartPointerTimer(POINTER_TIMEOUT)
+        );
+
+        machine.set_timings(KeyborgTimings {
+            pen_suppression: 300,
+            ..timings
+        });
+        assert_eq!(
+            machine.handle(pointer(PointerType::Pen))[0],
+            ModalityEffect::StartPointerTimer(300)
+        );
+    }
+
+    #[test]
+    fn set_forces_the_state() {
+        let mut machine = ModalityMachine::default();
+        machine.handle(MOUSE_DOWN);
+
+        assert_eq!(
+            machine.handle(ModalityEvent::Set(true)),
+            changed(InputModality::Keyboard)
+        );
+        assert_eq!(machine.handle(ModalityEvent::Set(true)), vec![]);
+        assert_eq!(
+            machine.handle(ModalityEvent::Set(false)),
+            vec![
+                ModalityEffect::ModalityChanged(InputModality::Unknown),
+                ModalityEffect::Changed(false)
+            ]
+        );
+    }
+
+    #[test]
+    fn locks_pin_the_state_until_released() {
+        let mut machine = ModalityMachine::default();
+
+        assert_eq!(
+            machine.handle(ModalityEvent::Lock {
+                id: 1,
+                is_navigating_with_keyboard: true,
+            }),
+            changed(InputModality::Keyboard)
+        );
+        assert_eq!(machine.handle(MOUSE_DOWN), vec![]);
+        assert_eq!(machine.handle(ModalityEvent::Set(false)), vec![]);
+        assert!(machine.is_navigating_with_keyboard());
+
+        machine.handle(ModalityEvent::Lock {
+            id: 2,
+            is_navigating_with_keyboard: false,
+        });
+        assert!(!machine.is_navigating_with_keyboard());
+
+        assert_eq!(
+            machine.handle(ModalityEvent::Unlock { id: 2 }),
+            changed(InputModality::Keyboard)
+        );
+        assert_eq!(machine.handle(ModalityEvent::Unlock { id: 1 }), vec![]);
+        assert!(!machine.is_locked());
+        assert_eq!(
+            machine.handle(MOUSE_DOWN),
+            vec![
+                ModalityEffect::StartPointerTimer(POINTER_TIMEOUT),
+                ModalityEffect::ModalityChanged(InputModality::Mouse),
+                ModalityEffect::Changed(false)
+            ]
+        );
+    }
+}
@@ -0,0 +1,219 @@
+use crate::{
+    editable::EditableClassifier,
+    indicator::Indicator,
+    key::{Key, KeyMatcher, KeyRule},
+    modality::{KeyborgTimings, TabPolicy},
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct KeyborgProps {
+    // Keys to be used to trigger keyboard navigation mode. By default, any key will trigger
+    // it. Could be limited to, for example, just Tab (or Tab and arrow keys).
+    pub(crate) trigger_keys: KeyRule,
+    // Keys to be used to dismiss keyboard navigation mode using keyboard (in addition to
+    // mouse clicks which dismiss it). For example, Esc could be used to dismiss.
+    pub(crate) dismiss_keys: KeyRule,
+    // Listen to Pointer Events instead of mouse and touch events.
+    pub(crate) use_pointer_events: bool,
+    pub(crate) timings: KeyborgTimings,
+    pub(crate) tab_policy: TabPolicy,
+    pub(crate) editable_classifiers: Vec<EditableClassifier>,
+    // Selectors of the subtrees in which keys and pointer events are ignored.
+    pub(crate) ignore_selectors: Vec<String>,
+    // Reflect the keyboard navigation mode on `document.documentElement`.
+    pub(crate) navigating_indicators: Vec<Indicator>,
+    // Marks the element focused while navigating with keyboard.
+    pub(crate) focus_visible_indicator: Option<Indicator>,
+    // Instrument the same-origin child frames when the focus enters them.
+    pub(crate) instrument_frames: bool,
+    // Share the state with the JavaScript keyborg library through `window.__keyborg`.
+    pub(crate) js_compat: bool,
+}
+
+impl KeyborgProps {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits the keys triggering the keyboard navigation mode, Tab always triggers it unless
+    /// excluded. Regardless of the held modifiers, use [`trigger`](Self::trigger) for chords.
+    ///
+    /// ```
+    /// # use keyborg::{Key, KeyborgProps};
+    /// let props = KeyborgProps::new().trigger_keys(["ArrowUp", "ArrowDown"]);
+    /// let props = KeyborgProps::new().trigger_keys([Key::code("KeyJ"), Key::code("KeyK")]);
+    /// ```
+    pub fn trigger_keys<K: Into<Key>>(self, keys: impl IntoIterator<Item = K>) -> Self {
+        keys.into_iter()
+            .fold(self, |props, key| props.trigger(KeyMatcher::key(key)))
+    }
+
+    /// Adds a key chord triggering the keyboard navigation mode.
+    ///
+    /// ```
+    /// # use keyborg::{KeyMatcher, KeyborgProps};
+    /// let props = KeyborgProps::new().trigger(KeyMatcher::arrows().without_modifiers());
+    /// ```
+    pub fn trigger(mut self, matcher: impl Into<KeyMatcher>) -> Self {
+        self.trigger_keys.include(matcher.into());
+        self
+    }
+
+    /// Keeps the key chord from triggering the keyboard navigation mode, including Tab.
+    ///
+    /// ```
+    /// # use keyborg::{KeyMatcher, KeyborgProps, Modifiers};
+    /// let props = KeyborgProps::new()
+    ///     .trigger_exclude(KeyMatcher::any_key().with(Modifiers::META))
+    ///     .trigger_exclude(KeyMatcher::any_key().with(Modifiers::CTRL));
+    /// ```
+    pub fn trigger_exclude(mut self, matcher: impl Into<KeyMatcher>) -> Self {
+        self.trigger_keys.exclude(matcher.into());
+        self
+    }
+
+    /// Keys dismissing the keyboard navigation mode when the focus does not move after them.
+    /// Regardless of the held modifiers, use [`dismiss`](Self::dismiss) for chords.
+    ///
+    /// ```
+    /// # use keyborg::KeyborgProps;
+    /// let props = KeyborgProps::new().dismiss_keys(["Escape"]);
+    /// ```
+    pub fn dismiss_keys<K: Into<Key>>(self, keys: impl IntoIterator<Item = K>) -> Self {
+        keys.into_iter()
+            .fold(self, |props, key| props.dismiss(KeyMatcher::key(key)))
+    }
+
+    /// Adds a key chord dismissing the keyboard navigation mode.
+    pub fn dismiss(mut self, matcher: impl Into<KeyMatcher>) -> Self {
+        self.dismiss_keys.include(matcher.into());
+        self
+    }
+
+    /// Keeps the key chord from dismissing the keyboard navigation mode.
+    pub fn dismiss_exclude(mut self, matcher: impl Into<KeyMatcher>) -> Self {
+        self.dismiss_keys.exclude(matcher.into());
+        self
+    }
+
+    /// Uses `pointerdown` with its `pointerType` instead of `mousedown` and the touch events,
+    /// so that pen input is told apart and the compatibility mouse events fired after touch
+    /// are not handled twice. Falls back to the mouse and touch events when `PointerEvent`
+    /// is not available.
+    pub fn use_pointer_events(mut self, use_pointer_events: bool) -> Self {
+        self.use_pointer_events = use_pointer_events;
+        self
+    }
+
+    /// Timing windows for the dismiss keys and each pointer type, can also be changed later
+    /// with [`Keyborg::set_timings`](crate::Keyborg::set_timings).
+    ///
+    /// ```
+    /// # use keyborg::{KeyborgProps, KeyborgTimings};
+    /// let props = KeyborgProps::new().timings(KeyborgTimings {
+    ///     dismiss_timeout: 1000,
+    ///     touch_suppression: 2000,
+    ///     ..Default::default()
+    /// });
+    /// ```
+    pub fn timings(mut self, timings: KeyborgTimings) -> Self {
+        self.timings = timings;
+        self
+    }
+
+    /// Extends the detection of editable elements, in which keys other than Tab do not
+    /// trigger the keyboard navigation mode. The classifier gets the focused element, looked
+    /// up through the open shadow roots, and returns `None` to fall back to the built-in rules
+    /// for `<input>` types, `<textarea>`, `contenteditable` and the textbox-like ARIA roles.
+    ///
+    /// ```
+    /// # use keyborg::KeyborgProps;
+    /// let props = KeyborgProps::new()
+    ///     .editable_classifier(|el| el.has_attribute("data-code-editor").then_some(true));
+    /// ```
+    pub fn editable_classifier(
+        mut self,
+        classifier: impl Fn(&web_sys::Element) -> Option<bool> + Send + Sync + 'static,
+    ) -> Self {
+        self.editable_classifiers
+            .push(EditableClassifier::new(classifier));
+        self
+    }
+
+    /// Ignores the keys and pointer events inside the elements matching the selector, in
+    /// addition to the elements with the [`KEYBORG_IGNORE`](crate::KEYBORG_IGNORE) attribute.
+    /// Useful for widgets consuming every key themselves, like games, terminals or code
+    /// editors. An invalid selector never matches.
+    ///
+    /// ```
+    /// # use keyborg::KeyborgProps;
+    /// let props = KeyborgProps::new()
+    ///     .ignore_selector("canvas")
+    ///     .ignore_selector(".xterm");
+    /// ```
+    pub fn ignore_selector(mut self, selector: impl Into<String>) -> Self {
+        self.ignore_selectors.push(selector.into());
+        self
+    }
+
+    /// How Tab pressed in an editable element is treated, by default it only triggers the
+    /// keyboard navigation mode when it was not prevented and moved the focus.
+    pub fn tab_policy(mut self, tab_policy: TabPolicy) -> Self {
+        self.tab_policy = tab_policy;
+        self
+    }
+
+    /// Sets the empty attribute on `document.documentElement` while navigating with keyboard,
+    /// removed when the keyborg core is disposed.
+    ///
+    /// ```
+    /// # use keyborg::KeyborgProps;
+    /// // [data-keyborg-navigating] :focus { outline: 2px solid; }
+    /// let props = KeyborgProps::new().navigating_attribute("data-keyborg-navigating");
+    /// ```
+    pub fn navigating_attribute(mut self, name: impl Into<String>) -> Self {
+        self.navigating_indicators
+            .push(Indicator::Attribute(name.into()));
+        self
+    }
+
+    /// Adds the class to `document.documentElement` while navigating with keyboard, removed
+    /// when the keyborg core is disposed.
+    pub fn navigating_class(mut self, name: impl Into<String>) -> Self {
+        self.navigating_indicators
+            .push(Indicator::Class(name.into()));
+        self
+    }
+
+    /// Sets the empty attribute on the element receiving `keyborg:focusin` while navigating
+    /// with keyboard, including inside shadow roots. It is removed on `keyborg:focusout` and
+    /// when the keyboard navigation mode ends.
+    ///
+    /// ```
+    /// # use keyborg::KeyborgProps;
+    /// // [data-keyborg-focus-visible] { outline: 2px solid; }
+    /// let props = KeyborgProps::new().focus_visible_attribute("data-keyborg-focus-visible");
+    /// ```
+    pub fn focus_visible_attribute(mut self, name: impl Into<String>) -> Self {
+        self.focus_visible_indicator = Some(Indicator::Attribute(name.into()));
+        self
+    }
+
+    /// Instruments the same-origin child frames when the focus enters them, so that the keys
+    /// pressed and the focus moved inside them change the keyboard navigation state shared
+    /// with the parent window. The frames having their own keyborg instance are left to it.
+    pub fn instrument_frames(mut self, instrument_frames: bool) -> Self {
+        self.instrument_frames = instrument_frames;
+        self
+    }
+
+    /// Shares the keyboard navigation state with the JavaScript keyborg library on the same
+    /// page, for example the one bundled with Tabster. When it is already set up in the
+    /// window, the keyborg core follows its state and its `keyborg:focusin` events instead of
+    /// installing a second `focus` override and listeners. Otherwise the state is exposed in
+    /// `window.__keyborg`, where the JavaScript keyborg instances created later adopt it.
+    pub fn js_compat(mut self, js_compat: bool) -> Self {
+        self.js_compat = js_compat;
+        self
+    }
+}
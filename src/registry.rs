@@ -0,0 +1,170 @@
+use std::{collections::HashMap, ops::Deref, sync::Arc};
+
+/// The cores shared by the instances created for the same key, the window for keyborg.
+/// The registry owns the cores, so a core is freed as soon as the caller drops the one
+/// handed back when its last instance is released.
+pub(crate) struct CoreRegistry<K, C, R> {
+    entries: Vec<(K, CoreEntry<C, R>)>,
+}
+
+struct CoreEntry<C, R> {
+    core: Arc<C>,
+    refs: HashMap<String, R>,
+}
+
+/// What releasing an instance did.
+#[derive(Debug)]
+pub(crate) enum Released<C> {
+    /// The instance was not registered, or was already released.
+    Unknown,
+    /// Other instances still use the core.
+    Shared,
+    /// It was the last instance, the core is handed back to be dropped after releasing
+    /// the locks.
+    Last(Arc<C>),
+}
+
+impl<K, C, R> Default for CoreRegistry<K, C, R> {
+    fn default() -> Self {
+        Self { entries: vec![] }
+    }
+}
+
+impl<K: Deref, C, R> CoreRegistry<K, C, R>
+where
+    K::Target: PartialEq,
+{
+    fn position(&self, key: &K::Target) -> Option<usize> {
+        self.entries.iter().position(|(k, _)| **k == *key)
+    }
+
+    pub fn core(&self, key: &K::Target) -> Option<&Arc<C>> {
+        let index = self.position(key)?;
+        Some(&self.entries[index].1.core)
+    }
+
+    pub fn refs(&self, key: &K::Target) -> impl Iterator<Item = &R> {
+        self.position(key)
+            .into_iter()
+            .flat_map(|index| self.entries[index].1.refs.values())
+    }
+
+    /// Registers the instance `id` of the key, using `core` when the key has none yet.
+    ///
+    /// @returns The core of the key
+    pub fn insert(&mut self, key: K, id: String, instance: R, core: Arc<C>) -> Arc<C> {
+        if let Some(index) = self.position(&key) {
+            let entry = &mut self.entries[index].1;
+            entry.refs.insert(id, instance);
+            return entry.core.clone();
+        }
+
+        let refs = HashMap::from([(id, instance)]);
+        self.entries.push((
+            key,
+            CoreEntry {
+                core: core.clone(),
+                refs,
+            },
+        ));
+        core
+    }
+
+    pub fn release(&mut self, key: &K::Target, id: &str) -> Released<C> {
+        let Some(index) = self.position(key) else {
+            return Released::Unknown;
+        };
+        let entry = &mut self.entries[index].1;
+        if entry.refs.remove(id).is_none() {
+            return Released::Unknown;
+        }
+        if !entry.refs.is_empty() {
+            return Released::Shared;
+        }
+
+        Released::Last(self.entries.remove(index).1.core)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct Core(Arc<AtomicUsize>);
+
+    impl Drop for Core {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    type Registry = CoreRegistry<String, Core, ()>;
+
+    fn insert(registry: &mut Registry, key: &str, id: &str, drops: &Arc<AtomicUsize>) -> Arc<Core> {
+        let core = Arc::new(Core(drops.clone()));
+        registry.insert(key.to_string(), id.to_string(), (), core)
+    }
+
+    #[test]
+    fn instances_of_a_key_share_the_core() {
+        let drops = Arc::default();
+        let mut registry = Registry::default();
+
+        let first = insert(&mut registry, "win", "k1", &drops);
+        let second = insert(&mut registry, "win", "k2", &drops);
+        assert!(Arc::ptr_eq(&first, &second));
+        // The core created for the second instance was not needed.
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+        assert_eq!(registry.refs("win").count(), 2);
+
+        let other = insert(&mut registry, "other", "k3", &drops);
+        assert!(!Arc::ptr_eq(&first, &other));
+    }
+
+    #[test]
+    fn the_core_is_freed_with_the_last_instance() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let mut registry = Registry::default();
+        drop(insert(&mut registry, "win", "k1", &drops));
+        drop(insert(&mut registry, "win", "k2", &drops));
+        let freed = drops.load(Ordering::SeqCst);
+
+        assert!(matches!(registry.release("win", "k1"), Released::Shared));
+        assert_eq!(drops.load(Ordering::SeqCst), freed);
+
+        let Released::Last(core) = registry.release("win", "k2") else {
+            panic!("the last instance hands the core back");
+        };
+        assert!(registry.core("win").is_none());
+        assert_eq!(Arc::strong_count(&core), 1);
+        drop(core);
+        assert_eq!(drops.load(Ordering::SeqCst), freed + 1);
+    }
+
+    #[test]
+    fn releasing_twice_does_nothing() {
+        let drops = Arc::default();
+        let mut registry = Registry::default();
+        drop(insert(&mut registry, "win", "k1", &drops));
+        drop(insert(&mut registry, "win", "k2", &drops));
+
+        assert!(matches!(registry.release("win", "k1"), Released::Shared));
+        assert!(matches!(registry.release("win", "k1"), Released::Unknown));
+        assert!(registry.core("win").is_some());
+        assert!(matches!(registry.release("win", "k2"), Released::Last(_)));
+        assert!(matches!(registry.release("win", "k2"), Released::Unknown));
+    }
+
+    #[test]
+    fn a_new_core_is_created_after_the_last_release() {
+        let drops = Arc::default();
+        let mut registry = Registry::default();
+        let first = insert(&mut registry, "win", "k1", &drops);
+        assert!(matches!(registry.release("win", "k1"), Released::Last(_)));
+
+        let second = insert(&mut registry, "win", "k2", &drops);
+        assert!(!Arc::ptr_eq(&first, &second));
+    }
+}
@@ -0,0 +1,231 @@
+use crate::subscription::{Subscribers, Subscription};
+use futures_channel::{mpsc, oneshot};
+use futures_core::{FusedStream, Stream};
+
+pub use futures_channel::oneshot::Canceled;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Mutex,
+    task::{Context, Poll},
+};
+
+/// The keyboard navigation state changes, returned by `Keyborg::changes`.
+/// Dropping the stream unsubscribes it.
+#[must_use = "streams do nothing unless polled"]
+pub struct KeyborgChanges {
+    receiver: mpsc::UnboundedReceiver<bool>,
+    _subscription: Subscription,
+}
+
+impl KeyborgChanges {
+    pub(crate) fn new(subscribers: &Subscribers<bool>) -> Self {
+        let (sender, receiver) = mpsc::unbounded();
+        let subscription = subscribers.subscribe(
+            move |is_navigating_with_keyboard| {
+                // The receiver is dropped together with the subscription.
+                let _ = sender.unbounded_send(is_navigating_with_keyboard);
+            },
+            false,
+        );
+
+        Self {
+            receiver,
+            _subscription: subscription,
+        }
+    }
+}
+
+impl Stream for KeyborgChanges {
+    type Item = bool;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<bool>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.receiver.size_hint()
+    }
+}
+
+impl FusedStream for KeyborgChanges {
+    fn is_terminated(&self) -> bool {
+        self.receiver.is_terminated()
+    }
+}
+
+/// Resolves once the keyboard navigation state equals the awaited one, returned by
+/// `Keyborg::wait_until`. Resolves to `Err(Canceled)` when the keyborg instance is disposed
+/// or dropped before.
+#[must_use = "futures do nothing unless awaited"]
+pub struct WaitUntil {
+    state: WaitUntilState,
+}
+
+enum WaitUntilState {
+    Ready,
+    Pending {
+        receiver: oneshot::Receiver<()>,
+        _subscription: Subscription,
+    },
+}
+
+impl WaitUntil {
+    pub(crate) fn new(subscribers: &Subscribers<bool>, current: bool, awaited: bool) -> Self {
+        // Once closed, the subscription below drops the sender right away.
+        if current == awaited && !subscribers.is_closed() {
+            return Self {
+                state: WaitUntilState::Ready,
+            };
+        }
+
+        let (sender, receiver) = oneshot::channel();
+        let sender = Mutex::new(Some(sender));
+        let subscription = subscribers.subscribe(
+            move |is_navigating_with_keyboard| {
+                if is_navigating_with_keyboard == awaited {
+                    if let Some(sender) = sender.lock().unwrap().take() {
+                        let _ = sender.send(());
+                    }
+                }
+            },
+            false,
+        );
+
+        Self {
+            state: WaitUntilState::Pending {
+                receiver,
+                _subscription: subscription,
+            },
+        }
+    }
+}
+
+impl Future for WaitUntil {
+    type Output = Result<(), Canceled>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let WaitUntilState::Pending { receiver, .. } = &mut self.state else {
+            return Poll::Ready(Ok(()));
+        };
+
+        match Pin::new(receiver).poll(cx) {
+            Poll::Ready(result) => {
+                // Unsubscribes right away rather than when the future is dropped.
+                self.state = WaitUntilState::Ready;
+                Poll::Ready(result)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::task::Waker;
+
+    fn poll_next(changes: &mut KeyborgChanges) -> Poll<Option<bool>> {
+        Pin::new(changes).poll_next(&mut Context::from_waker(Waker::noop()))
+    }
+
+    fn poll(wait: &mut WaitUntil) -> Poll<Result<(), Canceled>> {
+        Pin::new(wait).poll(&mut Context::from_waker(Waker::noop()))
+    }
+
+    #[test]
+    fn changes_are_streamed_in_order() {
+        let subscribers = Subscribers::default();
+        let mut changes = KeyborgChanges::new(&subscribers);
+        assert_eq!(poll_next(&mut changes), Poll::Pending);
+
+        subscribers.notify(true);
+        subscribers.notify(false);
+
+        assert_eq!(poll_next(&mut changes), Poll::Ready(Some(true)));
+        assert_eq!(poll_next(&mut changes), Poll::Ready(Some(false)));
+        assert_eq!(poll_next(&mut changes), Poll::Pending);
+    }
+
+    #[test]
+    fn dropping_changes_unsubscribes() {
+        let subscribers = Subscribers::default();
+        let changes = KeyborgChanges::new(&subscribers);
+        let probe = subscribers.subscribe(|_| {}, false);
+
+        drop(changes);
+        subscribers.notify(true);
+
+        assert!(probe.is_active());
+        assert_eq!(subscribers.len(), 1);
+    }
+
+    #[test]
+    fn wait_until_current_state_is_ready() {
+        let subscribers = Subscribers::default();
+        let mut wait = WaitUntil::new(&subscribers, true, true);
+
+        assert_eq!(poll(&mut wait), Poll::Ready(Ok(())));
+        assert_eq!(subscribers.len(), 0);
+    }
+
+    #[test]
+    fn wait_until_resolves_on_awaited_state() {
+        let subscribers = Subscribers::default();
+        let mut wait = WaitUntil::new(&subscribers, false, true);
+        assert_eq!(poll(&mut wait), Poll::Pending);
+
+        subscribers.notify(false);
+        assert_eq!(poll(&mut wait), Poll::Pending);
+
+        subscribers.notify(true);
+        assert_eq!(poll(&mut wait), Poll::Ready(Ok(())));
+        assert_eq!(subscribers.len(), 0);
+    }
+
+    #[test]
+    fn wait_until_is_canceled_with_the_subscribers() {
+        let subscribers = Subscribers::default();
+        let mut wait = WaitUntil::new(&subscribers, false, true);
+        assert_eq!(poll(&mut wait), Poll::Pending);
+
+        drop(subscribers);
+        assert_eq!(poll(&mut wait), Poll::Ready(Err(Canceled)));
+    }
+
+    #[test]
+    fn wait_until_is_canceled_when_closed() {
+        let subscribers = Subscribers::default();
+        let mut wait = WaitUntil::new(&subscribers, false, true);
+
+        subscribers.close();
+        subscribers.notify(true);
+        assert_eq!(poll(&mut wait), Poll::Ready(Err(Canceled)));
+    }
+
+    #[test]
+    fn wait_until_once_closed_is_canceled() {
+        let subscribers = Subscribers::default();
+        subscribers.close();
+
+        for (current, awaited) in [(false, true), (false, false)] {
+            let mut wait = WaitUntil::new(&subscribers, current, awaited);
+            assert_eq!(poll(&mut wait), Poll::Ready(Err(Canceled)));
+        }
+    }
+
+    #[test]
+    fn changes_end_when_closed() {
+        let subscribers = Subscribers::default();
+        let mut changes = KeyborgChanges::new(&subscribers);
+        subscribers.notify(true);
+
+        subscribers.close();
+        assert_eq!(poll_next(&mut changes), Poll::Ready(Some(true)));
+        assert_eq!(poll_next(&mut changes), Poll::Ready(None));
+        assert!(changes.is_terminated());
+
+        let mut changes = KeyborgChanges::new(&subscribers);
+        assert_eq!(poll_next(&mut changes), Poll::Ready(None));
+    }
+}
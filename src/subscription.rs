@@ -0,0 +1,269 @@
+use std::sync::{Arc, RwLock, Weak};
+use wasm_bindgen::UnwrapThrowExt;
+
+type Callback<T> = Arc<dyn Fn(T) + Send + Sync>;
+
+struct Entry<T> {
+    id: usize,
+    once: bool,
+    callback: Callback<T>,
+}
+
+struct SubscriberList<T> {
+    last_id: usize,
+    entries: Vec<Entry<T>>,
+    // No callbacks are accepted once closed.
+    closed: bool,
+}
+
+trait Unsubscribe: Send + Sync {
+    fn contains(&self, id: usize) -> bool;
+
+    /// @returns Whether the callback was subscribed
+    fn unsubscribe(&self, id: usize) -> bool;
+}
+
+impl<T: 'static> Unsubscribe for RwLock<SubscriberList<T>> {
+    fn contains(&self, id: usize) -> bool {
+        self.read()
+            .unwrap_throw()
+            .entries
+            .iter()
+            .any(|entry| entry.id == id)
+    }
+
+    fn unsubscribe(&self, id: usize) -> bool {
+        let mut list = self.write().unwrap_throw();
+        let len = list.entries.len();
+        list.entries.retain(|entry| entry.id != id);
+        list.entries.len() != len
+    }
+}
+
+/// Callbacks subscribed to a value, which may subscribe and unsubscribe while being called.
+pub(crate) struct Subscribers<T>(Arc<RwLock<SubscriberList<T>>>);
+
+impl<T> Clone for Subscribers<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Default for Subscribers<T> {
+    fn default() -> Self {
+        Self(Arc::new(RwLock::new(SubscriberList {
+            last_id: 0,
+            entries: vec![],
+            closed: false,
+        })))
+    }
+}
+
+impl<T: Copy + 'static> Subscribers<T> {
+    pub fn subscribe(
+        &self,
+        callback: impl Fn(T) + Send + Sync + 'static,
+        once: bool,
+    ) -> Subscription {
+        let id = {
+            let mut list = self.0.write().unwrap_throw();
+            if list.closed {
+                // The callback is dropped right away.
+                return Subscription { id: 0, list: None };
+            }
+            list.last_id += 1;
+            let id = list.last_id;
+            list.entries.push(Entry {
+                id,
+                once,
+                callback: Arc::new(callback),
+            });
+            id
+        };
+
+        let list: Arc<dyn Unsubscribe> = self.0.clone();
+        Subscription {
+            id,
+            list: Some(Arc::downgrade(&list)),
+        }
+    }
+
+    /// Calls the callbacks subscribed at the time of the call, skipping the ones removed
+    /// by an earlier callback.
+    pub fn notify(&self, value: T) {
+        let entries = self
+            .0
+            .read()
+            .unwrap_throw()
+            .entries
+            .iter()
+            .map(|entry| (entry.id, entry.once, entry.callback.clone()))
+            .collect::<Vec<_>>();
+
+        for (id, once, callback) in entries {
+            if once {
+                if !self.0.unsubscribe(id) {
+                    continue;
+                }
+            } else if !self.0.contains(id) {
+                continue;
+            }
+
+            callback(value);
+        }
+    }
+
+    /// Removes all the callbacks, the ones subscribed later are dropped right away.
+    pub fn close(&self) {
+        // The callbacks are dropped after releasing the lock.
+        let entries = {
+            let mut list = self.0.write().unwrap_throw();
+            list.closed = true;
+            std::mem::take(&mut list.entries)
+        };
+        drop(entries);
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.0.read().unwrap_throw().closed
+    }
+
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.0.read().unwrap_throw().entries.len()
+    }
+}
+
+/// A subscribed callback, which is removed when this is dropped or cancelled.
+#[must_use = "the callback is unsubscribed when the subscription is dropped, use `detach` to keep it"]
+pub struct Subscription {
+    id: usize,
+    list: Option<Weak<dyn Unsubscribe>>,
+}
+
+impl Subscription {
+    /// Removes the callback, same as dropping the subscription.
+    pub fn cancel(self) {}
+
+    /// Keeps the callback subscribed for as long as the keyborg instance lives.
+    pub fn detach(mut self) {
+        self.list = None;
+    }
+
+    /// @returns Whether the callback is still subscribed, one-shot callbacks are removed
+    /// once called
+    pub fn is_active(&self) -> bool {
+        self.list
+            .as_ref()
+            .and_then(Weak::upgrade)
+            .is_some_and(|list| list.contains(self.id))
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Some(list) = self.list.take().and_then(|list| list.upgrade()) {
+            list.unsubscribe(self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    type Calls = Arc<Mutex<Vec<&'static str>>>;
+
+    fn record(calls: &Calls, name: &'static str) -> impl Fn(bool) + Send + Sync + 'static {
+        let calls = calls.clone();
+        move |_| calls.lock().unwrap().push(name)
+    }
+
+    #[test]
+    fn dropping_and_cancelling_unsubscribe() {
+        let subscribers = Subscribers::<bool>::default();
+        let calls = Calls::default();
+
+        let a = subscribers.subscribe(record(&calls, "a"), false);
+        let b = subscribers.subscribe(record(&calls, "b"), false);
+        subscribers.subscribe(record(&calls, "c"), false).detach();
+        subscribers.notify(true);
+
+        drop(a);
+        assert!(b.is_active());
+        b.cancel();
+        subscribers.notify(true);
+
+        assert_eq!(*calls.lock().unwrap(), ["a", "b", "c", "c"]);
+    }
+
+    #[test]
+    fn once_is_called_once() {
+        let subscribers = Subscribers::<bool>::default();
+        let calls = Calls::default();
+
+        let once = subscribers.subscribe(record(&calls, "once"), true);
+        subscribers.notify(true);
+        subscribers.notify(false);
+
+        assert!(!once.is_active());
+        assert_eq!(*calls.lock().unwrap(), ["once"]);
+    }
+
+    #[test]
+    fn unsubscribing_during_delivery() {
+        let subscribers = Subscribers::<bool>::default();
+        let calls = Calls::default();
+        let later = Arc::new(Mutex::new(None::<Subscription>));
+        let own = Arc::new(Mutex::new(None::<Subscription>));
+
+        let first = subscribers.subscribe(
+            {
+                let later = later.clone();
+                let own = own.clone();
+                let record = record(&calls, "first");
+                move |value| {
+                    record(value);
+                    // Removes itself and a callback which has not been called yet.
+                    own.lock().unwrap().take();
+                    later.lock().unwrap().take();
+                }
+            },
+            false,
+        );
+        *own.lock().unwrap() = Some(first);
+        *later.lock().unwrap() = Some(subscribers.subscribe(record(&calls, "later"), false));
+        let _kept = subscribers.subscribe(record(&calls, "kept"), false);
+
+        subscribers.notify(true);
+        subscribers.notify(true);
+
+        assert_eq!(*calls.lock().unwrap(), ["first", "kept", "kept"]);
+    }
+
+    #[test]
+    fn subscription_outliving_subscribers() {
+        let subscribers = Subscribers::<bool>::default();
+        let subscription = subscribers.subscribe(|_| {}, false);
+
+        drop(subscribers);
+        assert!(!subscription.is_active());
+    }
+
+    #[test]
+    fn subscribing_once_closed() {
+        let subscribers = Subscribers::<bool>::default();
+        let calls = Calls::default();
+        let before = subscribers.subscribe(record(&calls, "before"), false);
+
+        subscribers.close();
+        let after = subscribers.subscribe(record(&calls, "after"), false);
+        subscribers.notify(true);
+
+        assert!(!before.is_active());
+        assert!(!after.is_active());
+        assert_eq!(subscribers.len(), 0);
+        assert!(calls.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,98 @@
+use crate::{binding::KeyborgBinding, InputModality, KeyborgProps};
+use ::yew::prelude::*;
+use send_wrapper::SendWrapper;
+use wasm_bindgen::UnwrapThrowExt;
+
+#[derive(Clone, Copy, PartialEq)]
+struct KeyborgContext {
+    is_navigating_with_keyboard: bool,
+    modality: InputModality,
+}
+
+/// Creates a keyborg instance when `enabled`, in an effect so nothing runs during server
+/// rendering. It is disposed when the component is destroyed.
+#[hook]
+fn use_keyborg_binding(enabled: bool, props: Option<KeyborgProps>) -> KeyborgContext {
+    let is_navigating_with_keyboard = use_state_eq(|| false);
+    let modality = use_state_eq(InputModality::default);
+
+    {
+        let is_navigating_with_keyboard = is_navigating_with_keyboard.clone();
+        let modality = modality.clone();
+        use_effect_with(enabled, move |enabled| {
+            let binding = enabled.then(|| {
+                let binding = KeyborgBinding::new(
+                    web_sys::window().unwrap_throw(),
+                    props,
+                    {
+                        let setter = SendWrapper::new(is_navigating_with_keyboard.setter());
+                        move |value| setter.set(value)
+                    },
+                    {
+                        let setter = SendWrapper::new(modality.setter());
+                        move |value| setter.set(value)
+                    },
+                );
+                is_navigating_with_keyboard.set(binding.is_navigating_with_keyboard());
+                modality.set(binding.current_modality());
+                binding
+            });
+
+            move || drop(binding)
+        });
+    }
+
+    KeyborgContext {
+        is_navigating_with_keyboard: *is_navigating_with_keyboard,
+        modality: *modality,
+    }
+}
+
+#[derive(Properties)]
+pub struct KeyborgProviderProps {
+    /// Only read when the provider is created.
+    #[prop_or_default]
+    pub props: Option<KeyborgProps>,
+    #[prop_or_default]
+    pub children: Html,
+}
+
+impl PartialEq for KeyborgProviderProps {
+    fn eq(&self, other: &Self) -> bool {
+        self.children == other.children
+    }
+}
+
+/// Provides a keyborg instance to its children, shared by `use_keyborg` and
+/// `use_keyborg_modality`.
+#[function_component]
+pub fn KeyborgProvider(props: &KeyborgProviderProps) -> Html {
+    let context = use_keyborg_binding(true, props.props.clone());
+
+    html! {
+        <ContextProvider<KeyborgContext> {context}>
+            {props.children.clone()}
+        </ContextProvider<KeyborgContext>>
+    }
+}
+
+#[hook]
+fn use_keyborg_context() -> KeyborgContext {
+    let provided = use_context::<KeyborgContext>();
+    let own = use_keyborg_binding(provided.is_none(), None);
+
+    provided.unwrap_or(own)
+}
+
+/// @returns Whether the user is navigating with keyboard, using the provided keyborg
+/// instance or creating one scoped to the current component.
+#[hook]
+pub fn use_keyborg() -> bool {
+    use_keyborg_context().is_navigating_with_keyboard
+}
+
+/// @returns How the user is currently interacting with the page
+#[hook]
+pub fn use_keyborg_modality() -> InputModality {
+    use_keyborg_context().modality
+}